@@ -0,0 +1,102 @@
+// compare two files, e.g. raw and processed reads or two basecaller versions
+//
+// the table stats of both files side by side with the differences, then the read ids that are
+// only in one of the files or in both but with a different length or quality. The ids of the
+// first file are kept as hashes in memory. The id rows count distinct ids, the first read of an id
+// is compared and the further reads with the same id are counted as duplicates
+use crate::error::{self, Error};
+use crate::modules::id_hash;
+use crate::stats::TableStats;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+// length and quality hash of a read of the first file
+struct Read {
+    len: usize,
+    qual: u128,
+    // seen in the second file
+    found: bool,
+}
+
+// a and b are the files with their (empty) TableStats
+pub fn compare(a: (&str, TableStats), b: (&str, TableStats), columns: &[String], header: bool, out: &mut dyn Write) -> Result<(), Error> {
+    let (file_a, mut stats_a) = a;
+    let (file_b, mut stats_b) = b;
+
+    let mut reads: HashMap<u128, Read> = HashMap::new();
+    let mut duplicates_a = 0;
+    let mut records = kseq::parse_path(file_a).map_err(error::input_error)?;
+    while let Some(record) = records.iter_record()? {
+        let (seq, qual) = (record.seq().as_bytes(), record.qual().as_bytes());
+        stats_a.add(seq, qual, record.is_fasta());
+        match reads.entry(id_hash(record.head().as_bytes())) {
+            Entry::Occupied(_) => duplicates_a += 1,
+            Entry::Vacant(entry) => {
+                entry.insert(Read { len: seq.len(), qual: id_hash(qual), found: false });
+            }
+        }
+    }
+
+    let (mut only_b, mut both, mut changed_length, mut changed_quality, mut duplicates_b) = (0, 0, 0, 0, 0);
+    // ids of the second file that are not in the first, to count them once
+    let mut new_ids: HashSet<u128> = HashSet::new();
+    let mut records = kseq::parse_path(file_b).map_err(error::input_error)?;
+    while let Some(record) = records.iter_record()? {
+        let (seq, qual) = (record.seq().as_bytes(), record.qual().as_bytes());
+        stats_b.add(seq, qual, record.is_fasta());
+        let id = id_hash(record.head().as_bytes());
+        match reads.get_mut(&id) {
+            Some(read) if !read.found => {
+                read.found = true;
+                both += 1;
+                if read.len != seq.len() {
+                    changed_length += 1;
+                }
+                if read.qual != id_hash(qual) {
+                    changed_quality += 1;
+                }
+            }
+            Some(_) => duplicates_b += 1,
+            None if new_ids.insert(id) => only_b += 1,
+            None => duplicates_b += 1,
+        }
+    }
+    let only_a = reads.values().filter(|r| !r.found).count();
+
+    if header {
+        writeln!(out, "column\t{}\t{}\tdelta\tdelta_percent", file_a, file_b)?;
+    }
+    let columns: Vec<String> = columns.iter().filter(|c| *c != "file").cloned().collect();
+    let row_a = stats_a.row(file_a, &columns);
+    let row_b = stats_b.row(file_b, &columns);
+    for ((column, x), y) in columns.iter().zip(row_a.split('\t')).zip(row_b.split('\t')) {
+        let (delta, percent) = match (x.parse::<f64>(), y.parse::<f64>()) {
+            (Ok(x), Ok(y)) if x != 0.0 => (format_delta(y - x), format!("{:.2}", (y - x) / x * 100.0)),
+            (Ok(x), Ok(y)) => (format_delta(y - x), "NA".to_string()),
+            _ => ("NA".to_string(), "NA".to_string()),
+        };
+        writeln!(out, "{}\t{}\t{}\t{}\t{}", column, x, y, delta, percent)?;
+    }
+
+    if header {
+        writeln!(out, "ids\treads")?;
+    }
+    writeln!(out, "only_in_first\t{}", only_a)?;
+    writeln!(out, "only_in_second\t{}", only_b)?;
+    writeln!(out, "in_both\t{}", both)?;
+    writeln!(out, "changed_length\t{}", changed_length)?;
+    writeln!(out, "changed_quality\t{}", changed_quality)?;
+    writeln!(out, "duplicates_in_first\t{}", duplicates_a)?;
+    writeln!(out, "duplicates_in_second\t{}", duplicates_b)?;
+    Ok(())
+}
+
+// whole numbers without decimals, e.g. reads and bases
+fn format_delta(x: f64) -> String {
+    if x.fract() == 0.0 {
+        format!("{:.0}", x)
+    } else {
+        format!("{:.2}", x)
+    }
+}
@@ -0,0 +1,81 @@
+// errors while processing a file, every class of error has its own exit code
+//
+// 0 success
+// 1 only warnings were found (--validate)
+// 2 invalid arguments or option values
+// 3 invalid input data (not a valid fastq/fasta file)
+// 4 the input can not be opened or read
+// 5 the output can not be written
+use kseq::record::ParseError;
+use std::{fmt, io};
+
+pub const EXIT_ARGUMENT: i32 = 2;
+pub const EXIT_INVALID_INPUT: i32 = 3;
+pub const EXIT_INPUT: i32 = 4;
+pub const EXIT_OUTPUT: i32 = 5;
+
+#[derive(Debug)]
+pub enum Error {
+    // e.g. --nx 2 or an unknown table column
+    Argument(String),
+    // the input file, or a file given as option value, can not be opened or read
+    Input(io::Error),
+    // the input is not a valid fastq/fasta file
+    InvalidInput(String),
+    // e.g. the pipe was closed
+    Output(io::Error),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Argument(_) => EXIT_ARGUMENT,
+            Error::InvalidInput(_) => EXIT_INVALID_INPUT,
+            Error::Input(_) => EXIT_INPUT,
+            Error::Output(_) => EXIT_OUTPUT,
+        }
+    }
+
+    // these errors would be the same for every file, so there is no point in going on
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Error::Argument(_) | Error::Output(_))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Argument(message) => write!(f, "{}", message),
+            Error::Input(err) => write!(f, "can not read input: {}", err),
+            Error::InvalidInput(message) => write!(f, "invalid input: {}", message),
+            Error::Output(err) => write!(f, "can not write output: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// io errors while processing a file come from writing, reading goes through kseq
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Output(err)
+    }
+}
+
+// an error reading the input, broken compressed data or a file that is not fasta/fastq (kseq
+// reports these as io errors) is invalid input and not a read error
+pub fn input_error(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput | io::ErrorKind::UnexpectedEof => Error::InvalidInput(err.to_string()),
+        _ => Error::Input(err),
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        match err {
+            ParseError::Io(err) => input_error(err),
+            other => Error::InvalidInput(other.to_string()),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Illumina (Casava 1.8+) read headers
+//
+// @instrument:run:flowcell:lane:tile:x:y read:filtered:control:index
+// e.g. @M00123:45:000000000-ABCDE:1:1101:15589:1331 1:N:0:ATCACG+GTAAGG, filtered is Y for reads
+// that did not pass the chastity filter
+
+pub struct Header<'a> {
+    pub lane: &'a str,
+    // lane and tile together as lane:tile, tile numbers repeat in every lane
+    pub tile: &'a str,
+    pub filtered: bool,
+    pub index: &'a str,
+}
+
+// None if the header is not in the Casava 1.8 format
+pub fn parse<'a>(head: &'a str, des: &'a str) -> Option<Header<'a>> {
+    let fields: Vec<&str> = head.split(':').collect();
+    if fields.len() != 7 {
+        return None;
+    }
+    let lane_start = fields[..3].iter().map(|f| f.len() + 1).sum::<usize>();
+    let tile_end = lane_start + fields[3].len() + 1 + fields[4].len();
+    let mut comment = des.split_whitespace().next()?.splitn(4, ':');
+    let (_read, filtered, _control, index) = (comment.next()?, comment.next()?, comment.next()?, comment.next()?);
+    Some(Header {
+        lane: fields[3],
+        tile: &head[lane_start..tile_end],
+        filtered: filtered == "Y",
+        index,
+    })
+}
+
+// value of lane, tile or index for --group_by, None for other keys or other headers
+pub fn field<'a>(head: &'a str, des: &'a str, key: &str) -> Option<&'a str> {
+    let header = parse(head, des)?;
+    match key {
+        "lane" => Some(header.lane),
+        "tile" => Some(header.tile),
+        "index" => Some(header.index),
+        _ => None,
+    }
+}
+
+pub fn is_field(key: &str) -> bool {
+    matches!(key, "lane" | "tile" | "index")
+}
+
+// reads that did not pass the filter (Y), false for other headers
+pub fn is_filtered(head: &str, des: &str) -> bool {
+    parse(head, des).is_some_and(|h| h.filtered)
+}
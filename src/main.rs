@@ -1,14 +1,27 @@
 use bio::seq_analysis::gc::gc_content;
 use modules::write_fastq;
 use regex::{bytes::RegexSet, Regex};
-use std::{fs, io::BufRead, io::BufReader, process, time::Duration};
+use std::{collections::{BTreeMap, HashMap}, fs, io, io::BufRead, io::BufReader, io::Write, process, str::FromStr, time::Duration};
 use indicatif::{HumanCount, ProgressBar};
 use kseq::parse_path;
 
 //extern crate clap;
-use clap::{App, Arg, ArgGroup};
+use clap::{App, Arg, ArgGroup, ArgMatches};
 // own functions
+mod compare;
+mod error;
+mod illumina;
 mod modules;
+mod nanopore;
+mod parallel;
+mod pipeline;
+mod report;
+mod stats;
+mod validate;
+mod watch;
+
+use error::Error;
+use stats::{LengthCounts, TableStats};
 
 fn main() {
     let matches = App::new("faster")
@@ -23,6 +36,90 @@ fn main() {
                             .short('t')
                             .long("table")
                             .help("Output a (tab separated) table with statistics"))
+                        .arg(Arg::with_name("columns")
+                            .long("columns")
+                            .takes_value(true)
+                            .help("Comma separated list of columns for the table output, in the desired order, e.g. file,reads,N10,N50,N90. Length percentiles are P<x>, e.g. P5"))
+                        .arg(Arg::with_name("percentiles")
+                            .long("percentiles")
+                            .takes_value(true)
+                            .help("Comma separated list of read length percentiles added to the table output, e.g. 5,95"))
+                        .arg(Arg::with_name("genome_size")
+                            .long("genome_size")
+                            .alias("genome-size")
+                            .takes_value(true)
+                            .help("Genome size for NG50 and the expected depth, e.g. 4.6m or 3g. Adds the columns NG50, LG50, depth (bases / genome size) and depth_10k (bases in reads of at least 10 kb / genome size) to the default table columns. With --columns, these and other cutoffs and NG<x>/LG<x> are selected there, e.g. NG50,NG90,depth_50k"))
+                        .arg(Arg::with_name("total")
+                            .long("total")
+                            .requires("table")
+                            .help("Add a row 'total' to the table output, with the statistics over the reads of all input files"))
+                        .arg(Arg::with_name("watch")
+                            .long("watch")
+                            .requires("table")
+                            // the cumulative row is the total, and watching has no end to write files at
+                            .conflicts_with_all(&["save_stats", "html", "total"])
+                            .help("Watch the input directories (e.g. fastq_pass/ during a run) and read every new fastq file once, when it is complete. After every scan with new files, a row with the cumulative statistics is printed (or written to --stats_file)"))
+                        .arg(Arg::with_name("interval")
+                            .long("interval")
+                            .takes_value(true)
+                            .requires("watch")
+                            .help("Seconds between two scans of the watched directories, default 60"))
+                        .arg(Arg::with_name("idle")
+                            .long("idle")
+                            .takes_value(true)
+                            .requires("watch")
+                            .help("Stop watching when no new files came for [integer] seconds, default is to watch until interrupted"))
+                        .arg(Arg::with_name("stats_file")
+                            .long("stats_file")
+                            .takes_value(true)
+                            .requires("watch")
+                            .help("Rewrite [file] with the header and the cumulative statistics instead of printing a new row"))
+                        .arg(Arg::with_name("save_stats")
+                            .long("save_stats")
+                            .takes_value(true)
+                            .help("Save the statistics of all input files together to [file] (gzipped text), with --table or --merge_stats. Saved statistics can be merged later with --merge_stats"))
+                        .arg(Arg::with_name("merge_stats")
+                            .long("merge_stats")
+                            .help("The inputs are files written with --save_stats (e.g. per chunk on different machines), merge them and output the table row 'total' for all the reads. The result is exactly the same as for reading all the reads at once"))
+                        .arg(Arg::with_name("html")
+                            .long("html")
+                            .takes_value(true)
+                            .requires("table")
+                            .help("Also write a self-contained html report to [file], with the table statistics and plots of the read lengths and qualities, one section per input file"))
+                        .arg(Arg::with_name("group_by")
+                            .long("group_by")
+                            .takes_value(true)
+                            .requires("table")
+                            .help("One table row per value of a header field, the value is the first column. Nanopore reads: barcode, runid, channel (ch) or any other key=value tag. Illumina reads: lane, tile or index. Reads without the field are in the group NA"))
+                        .arg(Arg::with_name("skip_filtered")
+                            .long("skip_filtered")
+                            .requires("table")
+                            .help("Illumina reads: leave out the reads flagged as filtered (Y in the header) from the table, their number goes to stderr"))
+                        .arg(Arg::with_name("channels")
+                            .long("channels")
+                            .help("Nanopore reads: output the number of reads and bases per channel (ch= tag), also for the channels without reads, e.g. to see the pore occupancy. All channels of the flow cell are listed, see --channel_count"))
+                        .arg(Arg::with_name("channel_count")
+                            .long("channel_count")
+                            .takes_value(true)
+                            .requires("channels")
+                            .help("Number of channels of the flow cell for --channels. By default the smallest of 126 (Flongle), 512 (MinION, GridION) and 3000 (PromethION) that has the highest channel with reads"))
+                        .arg(Arg::with_name("yield_time")
+                            .long("yield_time")
+                            .takes_value(true)
+                            .help("Nanopore reads: yield over run time in bins of [integer] minutes, from the start_time= tags. One row per bin (counted from the run start) with reads, bases, mean Q and N50 of the reads of all input files, followed by the same cumulative columns"))
+                        .arg(Arg::with_name("run_start")
+                            .long("run_start")
+                            .takes_value(true)
+                            .help("Start of the run for --yield_time and --select hours=..., e.g. 2019-10-30T10:18:17Z. By default the earliest start_time= of all input files, which needs an extra pass over them"))
+                        .arg(Arg::with_name("per_read")
+                            .long("per_read")
+                            .short('e')
+                            .help("Output a (tab separated) table with one line per read, see --fields"))
+                        .arg(Arg::with_name("fields")
+                            .long("fields")
+                            .takes_value(true)
+                            .requires("per_read")
+                            .help("Comma separated list of fields for --per_read, from file, id, description, length, gc, meanq, ee (expected errors), n_count, complexity (trinucleotide entropy, 0 to 1), first<k> and last<k> (first/last k bases). Default is id,length,gc,meanq"))
                         .arg(Arg::with_name("len")
                             .short('l')
                             .long("len")
@@ -35,11 +132,14 @@ fn main() {
                             .short('q')
                             .long("qscore")
                             .help("Output 'mean' read phred scores, one line per read. For this, the mean of the base probabilities for each read is calculated, and the result is converted back to a phred score"))
+                        .arg(Arg::with_name("len_qual")
+                            .long("len_qual")
+                            .help("Output a 2D histogram of read length against 'mean' read phred score as a long table (file, length_bin, q_bin, reads, bases). Lengths are binned on a log scale with 10 bins per power of ten, the phred scores in steps of 1"))
                         .arg(Arg::with_name("nx")
                             .long("nx")
                             .short('x')
                             .takes_value(true)
-                            .help("Output NX value, provide the desired NX value as 0.5 for e.g. N50 [numeric]"))
+                            .help("Output NX values and the matching LX (number of reads needed to reach NX), provide the desired NX value as 0.5 for e.g. N50 [numeric]. Several values can be given as a comma separated list, e.g. 0.1,0.5,0.9, and 'all' gives the full curve N1 to N100"))
                         .arg(Arg::with_name("qyield")
                             .long("qyield")
                             .short('y')
@@ -63,6 +163,22 @@ fn main() {
                             .takes_value(true)
                             .allow_hyphen_values(true) //important to parse negative integers
                             .help("Filter reads based on 'mean' read quality - use positive integer to filter for reads with BETTER quality than [integer] and negative integer to filter for reads with WORSE wuality than [integer]. Use range 8..60 for qscores"))
+                        .arg(Arg::with_name("filtern")
+                            .long("filtern")
+                            .takes_value(true)
+                            .help("Filter out reads with more than [number] N bases. A value below 1 is the maximum fraction of N bases, e.g. 0.05. The number of discarded reads goes to stderr"))
+                        .arg(Arg::with_name("iupac")
+                            .long("iupac")
+                            .requires("filtern")
+                            .help("With --filtern, count all IUPAC ambiguity codes (N, R, Y, K, M, S, W, B, D, H, V) instead of only N"))
+                        .arg(Arg::with_name("filterc")
+                            .long("filterc")
+                            .takes_value(true)
+                            .help("Filter out low-complexity reads (homopolymers, simple repeats), keep reads with a complexity of at least [number]. The complexity is the trinucleotide entropy scaled to 0..1, 0.5 is a good start. The number of discarded reads goes to stderr"))
+                        .arg(Arg::with_name("filtergc")
+                            .long("filtergc")
+                            .takes_value(true)
+                            .help("Keep only reads with a GC fraction between [min,max], e.g. 0.3,0.6 to remove host or contaminant reads with skewed GC. The number of discarded reads goes to stderr"))
                         .arg(Arg::with_name("trim_front")
                             .long("trim_front")
                             .short('a')
@@ -83,6 +199,46 @@ fn main() {
                             .short('z')
                             .takes_value(true)
                             .help("Output only reads whose id field matches a regex [string] pattern. The regex patterns are read from a file, one line per pattern."))
+                        .arg(Arg::with_name("select")
+                            .long("select")
+                            .takes_value(true)
+                            .help("Output only reads whose Nanopore header tags match all of the comma separated conditions, e.g. barcode=barcode03, ch=1-100 (range), ch!=5|6 (exclude), hours=0-24 (start_time within the first 24 hours of the run). The number of discarded reads goes to stderr"))
+                        .arg(Arg::with_name("to_fasta")
+                            .long("to_fasta")
+                            .short('c')
+                            .help("Convert to fasta, e.g. for BLAST or minimap2 indexes"))
+                        .arg(Arg::with_name("wrap")
+                            .long("wrap")
+                            .takes_value(true)
+                            .requires("to_fasta")
+                            .help("Wrap fasta sequences to [integer] bases per line, default is no wrapping"))
+                        .arg(Arg::with_name("to_fastq")
+                            .long("to_fastq")
+                            .takes_value(true)
+                            .help("Convert fasta to fastq, using the provided [character] as quality for all bases, e.g. 'I' for Q40"))
+                        .arg(Arg::with_name("compare")
+                            .long("compare")
+                            .help("Compare two files (e.g. raw and processed reads): the table statistics of both with the differences, and the number of read ids only in the first, only in the second and in both files, with a changed length or quality"))
+                        .arg(Arg::with_name("validate")
+                            .long("validate")
+                            .short('v')
+                            .help("Check the files for problems (sequence and quality lengths, alphabet, quality range, headers, duplicate ids, truncated records) and output them as a table with record number and byte offset. Exits with 0 if all files are fine, 1 for warnings only and 3 for errors"))
+                        .arg(Arg::with_name("phred")
+                            .long("phred")
+                            .takes_value(true)
+                            .help("Phred offset of the qualities, 33 (default), 64 (Illumina 1.3 to 1.7) or auto to detect it from the first reads of every file"))
+                        .arg(Arg::with_name("to_phred33")
+                            .long("to_phred33")
+                            .help("Convert the qualities to Phred+33, use --phred to give the offset of the input (64 or auto)"))
+                        .arg(Arg::with_name("keep_going")
+                            .long("keep_going")
+                            .short('k')
+                            .help("Report problems with an input file (can not be read, invalid fastq) and go on with the next file. The exit code is then the one of the last problem"))
+                        .arg(Arg::with_name("threads")
+                            .long("threads")
+                            .short('j')
+                            .takes_value(true)
+                            .help("Use [integer] threads. Several input files are processed concurrently (the output is still in input order), a single file in table mode is decompressed, parsed and summarized on separate threads"))
                         .arg(Arg::with_name("INPUT")
                             .help("Paths to fastq files, glob patterns and stdin can also be used")
                             .required(true)
@@ -92,335 +248,869 @@ fn main() {
 
                         // this group makes one and only one arg from the set required, avoid defining conflicts_with
                         .group(ArgGroup::with_name("group")
-                        .required(true).args(&["table", "len", "gc", "qscore", "filterl", "filterq", "sample", "trim_front", "trim_tail", "regex_string", "regex_file", "nx", "qyield"]))
+                        .required(true).args(&["table", "len", "gc", "qscore", "len_qual", "filterl", "filterq", "filtern", "filterc", "filtergc", "sample", "trim_front", "trim_tail", "regex_string", "regex_file", "select", "nx", "qyield", "to_fasta", "to_fastq", "per_read", "to_phred33", "validate", "channels", "yield_time", "compare", "merge_stats"]))
                         .get_matches();
 
     let infiles: Vec<&str> = matches.values_of("INPUT").unwrap().collect();
     
     // Check if the header for the table output needs to be printed once before the loop
-    if matches.is_present("table") && !matches.is_present("skip_header") {
-        println!("file\treads\tbases\tn_bases\tmin_len\tmax_len\tmean_len\tQ1\tQ2\tQ3\tN50\tQ20_percent\tQ30_percent");
+    // (this also checks the columns and fields before any file is read)
+    // (the watch mode prints its own header, or writes it to the stats file)
+    if matches.is_present("table") && !matches.is_present("skip_header") && !matches.is_present("watch") {
+        let columns = table_columns(&matches).unwrap_or_else(|e| exit_with(None, e));
+        match matches.value_of("group_by") {
+            Some(group) => println!("{}\t{}", group, columns.join("\t")),
+            None => println!("{}", columns.join("\t")),
+        }
+    }
+    if matches.is_present("len_qual") && !matches.is_present("skip_header") {
+        println!("file\tlength_bin\tq_bin\treads\tbases");
+    }
+    if matches.is_present("channels") && !matches.is_present("skip_header") {
+        println!("file\tchannel\treads\tbases");
+    }
+    if matches.is_present("yield_time") && !matches.is_present("skip_header") {
+        println!("file\ttime_h\treads\tbases\tmeanq\tN50\tcum_reads\tcum_bases\tcum_meanq\tcum_N50");
+    }
+    if matches.is_present("per_read") && !matches.is_present("skip_header") {
+        println!("{}", read_fields(&matches).unwrap_or_else(|e| exit_with(None, e)).join("\t"));
+    }
+    if matches.is_present("validate") && !matches.is_present("skip_header") {
+        println!("file\trecord\tbyte_offset\tlevel\tmessage");
     }
 
+    // compare works on the two files together, not file by file
+    if matches.is_present("compare") {
+        if let Err(e) = compare_files(&matches, &infiles) {
+            exit_with(Some(&infiles.join(", ")), e)
+        }
+        process::exit(0)
+    }
 
-    for infile in infiles {
-        let mut records = parse_path(&infile).unwrap();
-    
-        // case len
-        if matches.is_present("len") {
-            while let Some(record) = records.iter_record().unwrap() {
-                println!("{}", record.len());
+    let threads: usize = parse_value(&matches, "threads")
+        .unwrap_or_else(|e| exit_with(None, e))
+        .unwrap_or(1);
+    let keep_going = matches.is_present("keep_going");
+
+    if matches.is_present("save_stats") && !matches.is_present("table") && !matches.is_present("merge_stats") {
+        exit_with(None, Error::Argument("--save_stats needs --table or --merge_stats".to_string()))
+    }
+
+    // saved stats are merged to one table row, no reads are read
+    if matches.is_present("merge_stats") {
+        let mut total = TableStats {
+            genome_size: genome_size(&matches).unwrap_or_else(|e| exit_with(None, e)),
+            ..Default::default()
+        };
+        for infile in &infiles {
+            total.merge(&TableStats::load(infile).unwrap_or_else(|e| exit_with(Some(infile), e)));
+        }
+        let columns = table_columns(&matches).unwrap_or_else(|e| exit_with(None, e));
+        if !matches.is_present("skip_header") {
+            println!("{}", columns.join("\t"));
+        }
+        println!("{}", total.row("total", &columns));
+        if let Some(path) = matches.value_of("save_stats") {
+            total.save(path).unwrap_or_else(|e| exit_with(Some(path), e));
+        }
+        process::exit(0)
+    }
+
+    // watch the input directories instead of reading the input files once
+    if matches.is_present("watch") {
+        if let Err(e) = watch_dirs(&matches, &infiles, threads, keep_going) {
+            exit_with(None, e)
+        }
+        process::exit(0)
+    }
+
+    // times are relative to the start of the run, which is the same for all input files
+    let run_start = run_start(&matches, &infiles).unwrap_or_else(|e| exit_with(None, e));
+
+    // the total row merges the accumulators, so N50, quartiles etc. are over all reads. They are
+    // merged as the files are done, the stats per file are only kept for the html report
+    let merge_total = matches.is_present("total") || matches.is_present("save_stats");
+    let mut total = TableStats::default();
+    let mut tables: Vec<(String, TableStats)> = Vec::new();
+    // the yield bins of all files together, one series for the whole run
+    let mut yield_bins: BTreeMap<i64, nanopore::YieldBin> = BTreeMap::new();
+    let mut status = 0;
+    // worst result of --validate over all files
+    let mut validated = validate::EXIT_CLEAN;
+    let mut handle = |infile: &str, result: Result<Done, Error>| match result {
+        Ok(Done::Stats(stats)) => {
+            if merge_total {
+                total.merge(&stats);
             }
-            continue; // Go to the next file
-            
-        // case gc
-        } else if matches.is_present("gc") {
-            while let Some(record) = records.iter_record().unwrap() {
-                let seq = record.seq().as_bytes();
-                println!("{}", gc_content(seq));
+            if matches.is_present("html") {
+                tables.push((infile.to_string(), *stats));
+            }
+        }
+        Ok(Done::Validated(code)) => validated = validated.max(code),
+        Ok(Done::Yield(bins)) => {
+            for (bin, counts) in bins {
+                yield_bins.entry(bin).or_default().merge(&counts);
+            }
+        }
+        Ok(Done::Output) => {}
+        Err(e) if keep_going && !e.is_fatal() => {
+            eprintln!("{}: {}", infile, e);
+            status = e.exit_code();
+        }
+        Err(e) => exit_with(Some(infile), e),
+    };
+    if threads > 1 && infiles.len() > 1 {
+        parallel::run_ordered(&infiles, threads, keep_going, |infile, out| process_file(infile, &matches, out, 1, false, run_start), &mut handle);
+    } else {
+        let stdout = io::stdout();
+        for infile in &infiles {
+            handle(infile, process_file(infile, &matches, &mut stdout.lock(), threads, true, run_start));
+        }
+    }
+
+    if matches.is_present("yield_time") {
+        let name = if infiles.len() == 1 { infiles[0] } else { "all" };
+        write_yield(name, &yield_bins, &matches).unwrap_or_else(|e| exit_with(None, e));
+    }
+
+    if let Some(path) = matches.value_of("save_stats") {
+        total.save(path).unwrap_or_else(|e| exit_with(Some(path), e));
+    }
+    if matches.is_present("total") {
+        let columns = table_columns(&matches).unwrap_or_else(|e| exit_with(None, e));
+        if matches.is_present("group_by") {
+            print!("all\t");
+        }
+        println!("{}", total.row("total", &columns));
+    }
+
+    if let Some(path) = matches.value_of("html") {
+        let mut sections: Vec<(&str, &TableStats)> = tables.iter().map(|(infile, stats)| (infile.as_str(), stats)).collect();
+        if matches.is_present("total") {
+            sections.push(("total", &total));
+        }
+        let columns = table_columns(&matches).unwrap_or_else(|e| exit_with(None, e));
+        report::write_html(path, &sections, &columns).unwrap_or_else(|e| exit_with(Some(path), e));
+    }
+
+    process::exit(status.max(validated))
+}
+
+// report the error on stderr and exit with the exit code of its class
+fn exit_with(infile: Option<&str>, e: Error) -> ! {
+    match infile {
+        Some(infile) => eprintln!("{}: {}", infile, e),
+        None => eprintln!("{}", e),
+    }
+    process::exit(e.exit_code())
+}
+
+// value of an option parsed to T, None if the option was not given
+fn parse_value<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, Error> {
+    match matches.value_of(name) {
+        Some(value) => match value.trim().parse::<T>() {
+            Ok(x) => Ok(Some(x)),
+            Err(_) => Err(Error::Argument(format!("Invalid value '{}' for --{}", value, name))),
+        },
+        None => Ok(None),
+    }
+}
+
+// what process_file gives back to main, besides the output
+enum Done {
+    Output,
+    // the stats in table mode, for the total and the html report
+    Stats(Box<TableStats>),
+    // the exit code for the problems found by --validate
+    Validated(i32),
+    // reads per time bin of --yield_time, bins are counted from the run start
+    Yield(BTreeMap<i64, nanopore::YieldBin>),
+}
+
+// run the requested task on one input file, all output goes to out. The stats are returned in table mode
+// run_start is for the time of the reads, None to take the first read of the file
+fn process_file(infile: &str, matches: &ArgMatches, out: &mut dyn Write, threads: usize, progress: bool, run_start: Option<i64>) -> Result<Done, Error> {
+    // one file in table mode, decompression, parsing and stats are spread over several threads
+    // (this has to happen before parse_path, which already consumes the start of the input)
+    if threads > 1 && matches.is_present("table") && !matches.is_present("group_by") && !matches.is_present("skip_filtered") {
+        let pb = spinner(progress);
+        let stats = pipeline::table_stats(infile, threads, &empty_table_stats(matches, phred_offset(matches, infile)?)?, &pb)?;
+        pb.finish_and_clear();
+        writeln!(out, "{}", stats.row(infile, &table_columns(matches)?))?;
+        return Ok(Done::Stats(Box::new(stats)));
+    }
+
+    // validation reads the file itself, to know where the problems are
+    if matches.is_present("validate") {
+        let (records, warnings, errors) = validate::validate(infile, phred_offset(matches, infile)?, out)?;
+        eprintln!("{}: {} records, {} warnings, {} errors", infile, records, warnings, errors);
+        return Ok(Done::Validated(validate::exit_code(warnings, errors)));
+    }
+
+    let offset = phred_offset(matches, infile)?;
+    let mut records = parse_path(infile).map_err(error::input_error)?;
+
+    // case len
+    if matches.is_present("len") {
+        while let Some(record) = records.iter_record()? {
+            writeln!(out, "{}", record.len())?;
+        }
+        return Ok(Done::Output); // Go to the next file
+        
+    // case per read table
+    } else if matches.is_present("per_read") {
+        let fields = read_fields(matches)?;
+        while let Some(record) = records.iter_record()? {
+            let row: Vec<String> = fields
+                .iter()
+                .map(|f| match f.as_str() {
+                    "file" => infile.to_string(),
+                    _ => modules::read_field(&record, f, offset),
+                })
+                .collect();
+            writeln!(out, "{}", row.join("\t"))?;
+        }
+        return Ok(Done::Output); // Go to the next file
+
+    // case convert qualities to Phred+33
+    } else if matches.is_present("to_phred33") {
+        while let Some(record) = records.iter_record()? {
+            if record.is_fasta() {
+                write_fastq(record, out)?;
+                continue;
             }
-            continue; // Go to the next file
-
-        // case qscore
-        } else if matches.is_present("qscore") {
-            while let Some(record) = records.iter_record().unwrap() {
-                let mean_errorp = modules::qscore_probs(record.qual().as_bytes()) / record.seq().len() as f32;
-                //println!("{:.4}", mean_errorp);
-                println!("{:.4}", -10.0 * mean_errorp.log10());
+            // Solexa qualities below 0 become 0
+            let qual: Vec<u8> = record
+                .qual()
+                .as_bytes()
+                .iter()
+                .map(|&q| q.saturating_sub(offset).saturating_add(33))
+                .collect();
+            modules::write_fastq_qual(record, &qual, out)?;
+        }
+        return Ok(Done::Output); // Go to the next file
+
+    // case gc
+    } else if matches.is_present("gc") {
+        while let Some(record) = records.iter_record()? {
+            let seq = record.seq().as_bytes();
+            writeln!(out, "{}", gc_content(seq))?;
+        }
+        return Ok(Done::Output); // Go to the next file
+
+    // case qscore
+    } else if matches.is_present("qscore") {
+        while let Some(record) = records.iter_record()? {
+            // fasta records have no qualities
+            if record.is_fasta() {
+                writeln!(out, "NA")?;
+                continue;
+            }
+            let mean_errorp = modules::qscore_probs(record.qual().as_bytes(), offset) / record.seq().len() as f32;
+            //println!("{:.4}", mean_errorp);
+            writeln!(out, "{:.4}", -10.0 * mean_errorp.log10())?;
+        }
+        return Ok(Done::Output); // Go to the next file
+
+    // case length x qscore histogram
+    } else if matches.is_present("len_qual") {
+        // (length bin, q bin) -> (reads, bases)
+        let mut cells: BTreeMap<(i64, i64), (i64, i64)> = BTreeMap::new();
+        while let Some(record) = records.iter_record()? {
+            if record.is_fasta() {
+                return Err(Error::InvalidInput("the length x quality histogram needs fastq input".to_string()));
             }
-            continue; // Go to the next file
-
-        // case filter length
-        } else if matches.is_present("filterl") {
-            let filterlen = matches
-                .value_of("filterl")
-                .unwrap()
-                .trim()
-                .parse::<i32>();
-            // error on invalid input, rather than trying to guess
-            match filterlen {
-                Ok(x) => {
-                    while let Some(record) = records.iter_record().unwrap() {
-                        let seqlen = record.seq().len() as i32;
-                        if x >= 0 {
-                            if seqlen > x {
-                                write_fastq(record);
-                            }
-                        } else if x < 0 {
-                            if seqlen < x.abs() {
-                                write_fastq(record);
-                            }
+            let len = record.seq().len();
+            let mean_errorp = modules::qscore_probs(record.qual().as_bytes(), offset) / len as f32;
+            let qbin = (-10.0 * mean_errorp.log10()).floor() as i64;
+            let lenbin = (10.0 * (len.max(1) as f64).log10()).floor() as i64;
+            let cell = cells.entry((lenbin, qbin)).or_insert((0, 0));
+            cell.0 += 1;
+            cell.1 += len as i64;
+        }
+        // the length bins are given by their lower end
+        for ((lenbin, qbin), (reads, bases)) in cells {
+            writeln!(out, "{}\t{:.0}\t{}\t{}\t{}", infile, 10f64.powf(lenbin as f64 / 10.0).ceil(), qbin, reads, bases)?;
+        }
+        return Ok(Done::Output); // Go to the next file
+
+    // case filter length
+    } else if matches.is_present("filterl") {
+        // error on invalid input, rather than trying to guess
+        let x: i32 = parse_value(matches, "filterl")?.unwrap();
+        while let Some(record) = records.iter_record()? {
+            let seqlen = record.seq().len() as i32;
+            if x >= 0 {
+                if seqlen > x {
+                    write_fastq(record, out)?;
+                }
+            } else if x < 0 {
+                if seqlen < x.abs() {
+                    write_fastq(record, out)?;
+                }
+            }
+        }
+        return Ok(Done::Output); // Go to the next file
+    // case filter by qscore
+    } else if matches.is_present("filterq"){
+        let filterq: i32 = parse_value(matches, "filterq")?.unwrap();
+        match filterq {
+            q if (-60..=60).contains(&q) => {
+                // do stuff
+                while let Some(record) = records.iter_record()? {
+                    if record.is_fasta() {
+                        return Err(Error::InvalidInput("filtering by quality needs fastq input".to_string()));
+                    }
+                    let mean_errorp = modules::qscore_probs(record.qual().as_bytes(), offset) / record.seq().len() as f32;
+                    let mean_qscore = -10.0 * mean_errorp.log10();
+                    let q_f32 = q as f32;
+                    if q >= 0 {
+                        if mean_qscore > q_f32 {
+                            write_fastq(record, out)?;
+                        }
+                    } else if q < 0 {
+                        if mean_qscore < q_f32.abs() {
+                            write_fastq(record, out)?;
                         }
                     }
                 }
-                Err(e) => eprintln!("Did you use an integer for filter? The error is: '{}'", e),
             }
-            continue; // Go to the next file
-        // case filter by qscore
-        } else if matches.is_present("filterq"){
-            let filterq = matches
-                .value_of("filterq")
-                .unwrap()
-                .trim()
-                .parse::<i32>()
-                .expect("Failed to parse desired q value, please use integer between 8 and 60");
-            match filterq {
-                q if (-60..=60).contains(&q) => {
-                    // do stuff
-                    while let Some(record) = records.iter_record().unwrap() {
-                        let mean_errorp = modules::qscore_probs(record.qual().as_bytes()) / record.seq().len() as f32;
-                        let mean_qscore = -10.0 * mean_errorp.log10();
-                        let q_f32 = q as f32;
-                        if q >= 0 {
-                            if mean_qscore > q_f32 {
-                                write_fastq(record);
-                            }
-                        } else if q < 0 {
-                            if mean_qscore < q_f32.abs() {
-                                write_fastq(record);
-                            }
-                        }
+            _ => {
+                return Err(Error::Argument("The q value should be between 10 and 60".to_string()));
+            }
+        }
+        return Ok(Done::Output); // Go to the next file
+    // case filter by N content
+    } else if matches.is_present("filtern") {
+        let maxn: f64 = parse_value(matches, "filtern")?.unwrap();
+        if maxn < 0.0 {
+            return Err(Error::Argument("The maximum N count or fraction should not be negative".to_string()));
+        }
+        let iupac = matches.is_present("iupac");
+        let mut discarded: i64 = 0;
+        while let Some(record) = records.iter_record()? {
+            let seq = record.seq().as_bytes();
+            let n = if iupac { modules::get_ambiguous_bases(seq) } else { modules::get_n_bases(seq) };
+            // below 1 the value is a fraction of the read length, otherwise a count
+            let keep = if maxn < 1.0 {
+                n as f64 <= maxn * seq.len() as f64
+            } else {
+                n as f64 <= maxn
+            };
+            if keep {
+                write_fastq(record, out)?;
+            } else {
+                discarded += 1;
+            }
+        }
+        eprintln!("{}: {} reads discarded", infile, discarded);
+        return Ok(Done::Output); // Go to the next file
+    // case filter by GC range
+    } else if matches.is_present("filtergc") {
+        let (min, max) = gc_range(matches.value_of("filtergc").unwrap())?;
+        let mut discarded: i64 = 0;
+        while let Some(record) = records.iter_record()? {
+            let gc = gc_content(record.seq().as_bytes());
+            if gc >= min && gc <= max {
+                write_fastq(record, out)?;
+            } else {
+                discarded += 1;
+            }
+        }
+        eprintln!("{}: {} reads discarded", infile, discarded);
+        return Ok(Done::Output); // Go to the next file
+    // case filter by complexity
+    } else if matches.is_present("filterc") {
+        let min: f64 = parse_value(matches, "filterc")?.unwrap();
+        if !(0.0..=1.0).contains(&min) {
+            return Err(Error::Argument("The complexity should be between 0.0 and 1.0".to_string()));
+        }
+        let mut discarded: i64 = 0;
+        while let Some(record) = records.iter_record()? {
+            if modules::get_complexity(record.seq().as_bytes()) >= min {
+                write_fastq(record, out)?;
+            } else {
+                discarded += 1;
+            }
+        }
+        eprintln!("{}: {} reads discarded", infile, discarded);
+        return Ok(Done::Output); // Go to the next file
+    // case nx    
+    } else if matches.is_present("nx") {
+        let fractions = nx_fractions(matches.value_of("nx").unwrap())?;
+        // one pass over the reads, all NX values from the same length distribution
+        let mut lengths = LengthCounts::default();
+        while let Some(record) = records.iter_record()? {
+            lengths.add(record.seq().len() as i64);
+        }
+        for (x, (nx, lx)) in fractions.iter().zip(lengths.nx(&fractions)) {
+            // e.g. 0.125 is N12.5
+            let percent = (x * 100_000.0).round() / 1000.0;
+            writeln!(out, "N{}\t{}\tL{}\t{}", percent, nx, percent, lx)?;
+        }
+        return Ok(Done::Output); // Go to the next file
+    } else if matches.is_present("qyield") {
+        let qvalue: u8 = parse_value(matches, "qyield")?.unwrap();
+
+            match qvalue {
+                x if (8..=60).contains(&x) => {
+                    let mut bases: i64 = 0;
+                    let mut qualx: i64 = 0;
+                    let mut fasta = false;
+                    // do work
+                    while let Some(record) = records.iter_record()? {
+                        let len = record.seq().len() as i64;
+                        bases += len;
+                        fasta |= record.is_fasta();
+                        qualx += modules::get_qual_bases(record.qual().as_bytes(), offset + qvalue);
+                    }
+                    if fasta {
+                        writeln!(out, "Q{}\tNA", qvalue)?;
+                    } else {
+                        let qx = qualx as f64 / bases as f64 * 100.0;
+                        writeln!(out, "Q{}\t{:.2}", qvalue, qx)?;
                     }
                 }
                 _ => {
-                    eprintln!("The q value should be between 10 and 60");
-                    process::exit(1)
+                    return Err(Error::Argument("The qyield value should be between 10 and 60".to_string()));
                 }
             }
-            continue; // Go to the next file
-        // case nx    
-        } else if matches.is_present("nx") {
-            let nxvalue: f32 = matches
-                .value_of("nx")
-                .unwrap()
-                .trim()
-                .parse::<f32>()
-                .expect("Failed to parse desired NX value");
-
-                match nxvalue {
-                    x if (0.0..=1.0).contains(&x) => {
-                        // do work
-                        let mut lengths: Vec<i64> = Vec::new();
-
-                        while let Some(record) = records.iter_record().unwrap() {
-                            let len = record.seq().len() as i64;
-                            lengths.push(len);                    
+        return Ok(Done::Output); // Go to the next file
+    
+    } else if matches.is_present("sample") {
+        // parse fraction
+        let fraction: f32 = parse_value(matches, "sample")?.unwrap();
+
+            match fraction {
+                // see <https://stackoverflow.com/a/58434531/8040734>
+                x if (0.0..=1.0).contains(&x) => {
+                    let nth = 1 as f32/fraction; // 1/fraction gives step_by
+                    let mut recn = 0;
+                    while let Some(record) = records.iter_record()? {
+                        recn += 1;
+                        if recn != nth as i32 {
+                            continue;
                         }
-                        let nx = modules::get_nx(&mut lengths, 1.0 - nxvalue);
-                        let nx_value = nxvalue * 100.0;
-                        
-                        println!("N{}\t{}", nx_value as i16, nx);
-                    }
-                    _ => {
-                        eprintln!("The NX value should be between 0.1 and 1.0");
-                        process::exit(0)
+                        recn = 0;
+                        write_fastq(record, out)?;
                     }
                 }
-            continue; // Go to the next file
-        } else if matches.is_present("qyield") {
-            let qvalue = matches
-                .value_of("qyield")
-                .unwrap()
-                .trim()
-                .parse::<u8>()
-                .expect("Failed to parse desired q value");
-
-                match qvalue {
-                    x if (8..=60).contains(&x) => {
-                        let mut bases: i64 = 0;
-                        let mut qualx: i64 = 0;
-                        // do work
-                        while let Some(record) = records.iter_record().unwrap() {
-                            let len = record.seq().len() as i64;
-                            bases += len;
-                            qualx += modules::get_qual_bases(record.qual().as_bytes(), 33 + qvalue); // 33 offset
-                        }
-                        let qx = qualx as f64 / bases as f64 * 100.0;
-                        println!("Q{}\t{:.2}", qvalue, qx);
-                    }
-                    _ => {
-                        eprintln!("The qyield value should be between 10 and 60");
-                        process::exit(1)
-                    }
+                _ => {
+                    return Err(Error::Argument("The subsample fraction should be between 0.0 and 1.0".to_string()));
                 }
-            continue; // Go to the next file
+        }
+
+        return Ok(Done::Output); // Go to the next file
         
-        } else if matches.is_present("sample") {
-            // parse fraction
-            let fraction = matches
-                .value_of("sample")
-                .unwrap()
-                .trim()
-                .parse::<f32>()
-                .expect("Failed to parse sample fraction value!");
-
-                match fraction {
-                    // see <https://stackoverflow.com/a/58434531/8040734>
-                    x if (0.0..=1.0).contains(&x) => {
-                        let nth = 1 as f32/fraction; // 1/fraction gives step_by
-                        let mut recn = 0;
-                        while let Some(record) = records.iter_record().unwrap() {
-                            recn += 1;
-                            if recn != nth as i32 {
-                                continue;
-                            }
-                            recn = 0;
-                            write_fastq(record);
-                        }
-                    }
-                    _ => {
-                        eprintln!("The subsample fraction should be between 0.0 and 1.0");
-                        process::exit(0)
-                    }
-            }
+    } else if matches.is_present("trim_front") {
+        // parse trim value as usize
+        let trimvalue: usize = parse_value(matches, "trim_front")?.unwrap();
+
+        while let Some(record) = records.iter_record()? {
+
+            // the new sequence is trim..seq.len, reads shorter than trim become empty
+            let trimleft = trimvalue.min(record.len());
+            let len = record.len();
+            modules::write_trimmed(record, trimleft, len, out)?;
+        }
+        return Ok(Done::Output); // Go to the next file
+    } else if matches.is_present("trim_tail") {
+        let trimvalue: usize = parse_value(matches, "trim_tail")?.unwrap();
 
-            continue; // Go to the next file
+        while let Some(record) = records.iter_record()? {
             
-        } else if matches.is_present("trim_front") {
-            // parse trim value as usize
-            let trimvalue = matches
-                .value_of("trim_front")
-                .unwrap()
-                .trim()
-                .parse::<usize>()
-                .expect("failed to parse trim value!");
-
-            while let Some(record) = records.iter_record().unwrap() {
-
-                // the new sequence is trim..seq.len
-                let newseq = &record.seq()[trimvalue..];
-                let newqual = &record.qual()[trimvalue..];
-                
-                println!(
-                    "{} {}\n{}\n{}\n{}", 
-                    "@".to_string() + record.head(), record.des(), 
-                    newseq, 
-                    "+", 
-                    newqual
-                );
-            }
-            continue; // Go to the next file
-        } else if matches.is_present("trim_tail") {
-            let trimvalue = matches
-                .value_of("trim_tail")
-                .unwrap()
-                .trim()
-                .parse::<usize>()
-                .expect("failed to parse trim value!");
-
-            while let Some(record) = records.iter_record().unwrap() {
-                
-                // the new sequence is 0..seq.len - trim
-                let trimright = record.len() - trimvalue;
-                let newseq = &record.seq()[..trimright];
-                let newqual = &record.qual()[..trimright];
-
-                println!(
-                    "{} {}\n{}\n{}\n{}", 
-                    "@".to_string() + record.head(), record.des(), 
-                    newseq, 
-                    "+", 
-                    newqual
-                );
+            // the new sequence is 0..seq.len - trim, reads shorter than trim become empty
+            let trimright = record.len().saturating_sub(trimvalue);
+            modules::write_trimmed(record, 0, trimright, out)?;
+        }
+        return Ok(Done::Output); // Go to the next file
+    } else if matches.is_present("regex_string") {
+        // parse string
+        let string: &str = matches.value_of("regex_string").unwrap().trim();
+
+        let re = Regex::new(string)
+            .map_err(|e| Error::Argument(format!("Failed to construct regex from string: {}", e)))?;
+
+        while let Some(record) = records.iter_record()? {
+            let readid = record.head();
+            if re.is_match(readid) {
+                write_fastq(record, out)?;  
             }
-            continue; // Go to the next file
-        } else if matches.is_present("regex_string") {
-            // parse string
-            let string: &str = matches.value_of("regex_string").unwrap().trim();
+        }
+        return Ok(Done::Output); // Go to the next file
+    } else if matches.is_present("regex_file") {
+        //parse file
+        let refilepath = matches.value_of("regex_file").unwrap();
+        let refile = fs::File::open(refilepath)
+            .map_err(|e| Error::Input(io::Error::new(e.kind(), format!("{}: {}", refilepath, e))))?;
+        let re_reader = BufReader::new(refile);
+
+        // collect regex lines in a vec
+        let mut revec = Vec::new();
+        for line in re_reader.lines() {
+            //println!("line is: {}", line);
+            revec.push(line.map_err(Error::Input)?);
+        }
 
-            let re = Regex::new(string).expect("Failed to construct regex from string!");
+        let re_set = RegexSet::new(&revec)
+            .map_err(|e| Error::Argument(format!("Failed to construct regex from {}: {}", refilepath, e)))?;
+        // write record to stdout in case of match
+        while let Some(record) = records.iter_record()? {
+            let readid = record.head().as_bytes(); // as.bytes because RegexSet matches on bytes
 
-            while let Some(record) = records.iter_record().unwrap() {
-                let readid = record.head();
-                if re.is_match(readid) {
-                    write_fastq(record);  
+            if re_set.is_match(readid) {
+                write_fastq(record, out)?;
+            }
+        }
+        //println!("vector: {:?}", &revec);
+        return Ok(Done::Output); // Go to the next file
+    // case select by header tags
+    } else if matches.is_present("select") {
+        let conditions = nanopore::parse_conditions(matches.value_of("select").unwrap()).map_err(Error::Argument)?;
+        let mut selection = nanopore::Selection::new(conditions, run_start);
+        let mut discarded: i64 = 0;
+        while let Some(record) = records.iter_record()? {
+            if selection.matches(record.des()) {
+                write_fastq(record, out)?;
+            } else {
+                discarded += 1;
+            }
+        }
+        eprintln!("{}: {} reads discarded", infile, discarded);
+        return Ok(Done::Output); // Go to the next file
+    } else if matches.is_present("to_fasta") {
+        // 0 means no wrapping
+        let width: usize = parse_value(matches, "wrap")?.unwrap_or(0);
+
+        while let Some(record) = records.iter_record()? {
+            modules::write_fasta(record, width, out)?;
+        }
+        return Ok(Done::Output); // Go to the next file
+    } else if matches.is_present("to_fastq") {
+        let qchar = matches.value_of("to_fastq").unwrap();
+        // one printable character, phred 0 ('!') to 93 ('~')
+        match qchar.as_bytes() {
+            [q] if (b'!'..=b'~').contains(q) => {
+                while let Some(record) = records.iter_record()? {
+                    if record.is_fasta() {
+                        modules::write_fasta_as_fastq(record, *q as char, out)?;
+                    } else {
+                        write_fastq(record, out)?;
+                    }
                 }
             }
-            continue; // Go to the next file
-        } else if matches.is_present("regex_file") {
-            //parse file
-            let refilepath = matches.value_of("regex_file").unwrap();
-            let refile = fs::File::open(refilepath).expect("File not found!");
-            let re_reader = BufReader::new(refile);
-
-            // collect regex lines in a vec
-            let mut revec = Vec::new();
-            for line in re_reader.lines().map(|l| l.unwrap()) {
-                //println!("line is: {}", line);
-                revec.push(line);
+            _ => {
+                return Err(Error::Argument("The quality for to_fastq should be one character between '!' and '~'".to_string()));
             }
+        }
+        return Ok(Done::Output); // Go to the next file
 
-            let re_set = RegexSet::new(&revec).unwrap();
-            // write record to stdout in case of match
-            while let Some(record) = records.iter_record().unwrap() {
-                let readid = record.head().as_bytes(); // as.bytes because RegexSet matches on bytes
+    // case per channel read counts
+    } else if matches.is_present("channels") {
+        // reads and bases per channel number
+        let mut channels: BTreeMap<usize, (i64, i64)> = BTreeMap::new();
+        let mut untagged: i64 = 0;
+        while let Some(record) = records.iter_record()? {
+            match nanopore::tag(record.des(), "ch").and_then(|ch| ch.parse::<usize>().ok()) {
+                Some(ch) => {
+                    let counts = channels.entry(ch).or_insert((0, 0));
+                    counts.0 += 1;
+                    counts.1 += record.len() as i64;
+                }
+                None => untagged += 1,
+            }
+        }
+        // channels are numbered from 1, all channels of the flow cell are listed, also those without
+        // reads. Channels above the flow cell (or 0) are listed after them
+        let max_channel = channels.keys().next_back().copied();
+        let count = match parse_value::<usize>(matches, "channel_count")? {
+            Some(count) => count,
+            None => max_channel.map_or(0, nanopore::flowcell_channels),
+        };
+        for ch in 1..=count {
+            let (reads, bases) = channels.remove(&ch).unwrap_or((0, 0));
+            writeln!(out, "{}\t{}\t{}\t{}", infile, ch, reads, bases)?;
+        }
+        for (ch, (reads, bases)) in channels {
+            writeln!(out, "{}\t{}\t{}\t{}", infile, ch, reads, bases)?;
+        }
+        if untagged > 0 {
+            eprintln!("{}: {} reads without a ch= tag", infile, untagged);
+        }
+        return Ok(Done::Output); // Go to the next file
 
-                if re_set.is_match(readid) {
-                    write_fastq(record);
+    // case yield over time
+    } else if matches.is_present("yield_time") {
+        let size = yield_bin_size(matches)?;
+        // reads are not strictly ordered by time, bins before the run start are negative
+        let mut start = run_start;
+        let mut bins: BTreeMap<i64, nanopore::YieldBin> = BTreeMap::new();
+        let mut untagged: i64 = 0;
+        while let Some(record) = records.iter_record()? {
+            let time = match nanopore::tag(record.des(), "start_time").and_then(nanopore::parse_time) {
+                Some(time) => time,
+                None => {
+                    untagged += 1;
+                    continue;
                 }
+            };
+            let bin = (time - *start.get_or_insert(time)).div_euclid(size);
+            let error_sum = modules::qscore_probs(record.qual().as_bytes(), offset) as f64;
+            bins.entry(bin).or_default().add(record.len() as i64, error_sum, record.is_fasta());
+        }
+        if untagged > 0 {
+            eprintln!("{}: {} reads without a start_time= tag", infile, untagged);
+        }
+        return Ok(Done::Yield(bins)); // Go to the next file
+
+    // case table per group of reads (barcode, channel...), in the order of the group values
+    } else if matches.is_present("group_by") {
+        let key = nanopore::tag_key(matches.value_of("group_by").unwrap());
+        let empty = empty_table_stats(matches, offset)?;
+        let mut groups: HashMap<String, TableStats> = HashMap::new();
+        let pb = spinner(progress);
+        let mut reads: u64 = 0;
+        let skip_filtered = matches.is_present("skip_filtered");
+        let mut filtered: i64 = 0;
+
+        while let Some(record) = records.iter_record()? {
+            if skip_filtered && illumina::is_filtered(record.head(), record.des()) {
+                filtered += 1;
+                continue;
             }
-            //println!("vector: {:?}", &revec);
-            continue; // Go to the next file
-
-        // case table (only runs if table is requested and none of the other single-task options were matched)
-        } else if matches.is_present("table") {
-            // normal case, output table
-            let mut reads: i64 = 0;
-            let mut bases: i64 = 0;
-            let mut num_n: i32 = 0;
-            let mut qual20: i64 = 0;
-            let mut qual30: i64 = 0;
-            let mut minlen: i64 = i64::MAX;
-            let mut maxlen = 0;
-            let mut len_vector: Vec<i64> = Vec::new();
-            let pb = ProgressBar::new_spinner();
-            pb.enable_steady_tick(Duration::from_millis(120));
-
-            while let Some(record) = records.iter_record().unwrap() {
-                //let seq = record.seq();
-                let len = record.len() as i64; // here have to accomodate bigger numbers, as bases can get > 2^32
-
-                reads += 1;
-                bases += len;
-                num_n += modules::get_n_bases(record.seq().as_bytes());
-                qual20 += modules::get_qual_bases(record.qual().as_bytes(), 53); // 33 offset
-                qual30 += modules::get_qual_bases(record.qual().as_bytes(), 63);
-                minlen = len.min(minlen);
-                maxlen = len.max(maxlen);
-                len_vector.push(len);
-                let message = format!("Processed reads: {}", HumanCount(reads as u64).to_string());
-                pb.set_message(message);
+            let group = if illumina::is_field(key) {
+                illumina::field(record.head(), record.des(), key)
+            } else {
+                nanopore::tag(record.des(), key)
+            };
+            let group = group.unwrap_or("NA");
+            if !groups.contains_key(group) {
+                groups.insert(group.to_string(), empty.clone());
             }
+            let stats = groups.get_mut(group).unwrap();
+            stats.add(record.seq().as_bytes(), record.qual().as_bytes(), record.is_fasta());
+            reads += 1;
+            pb.set_message(format!("Processed reads: {}", HumanCount(reads)));
+        }
+        pb.finish_and_clear();
+        if skip_filtered {
+            eprintln!("{}: {} filtered reads skipped", infile, filtered);
+        }
 
-            let mean_len = modules::mean(&len_vector);
-            let quart1 = modules::quartiles(&mut len_vector, 1);
-            let quart2 = modules::quartiles(&mut len_vector, 2);
-            let quart3 = modules::quartiles(&mut len_vector, 3);
-            let n50 = modules::get_nx(&mut len_vector, 0.5); // use 0.1 for N90!!!
-            let q20 = qual20 as f64 / bases as f64 * 100.0;
-            let q30 = qual30 as f64 / bases as f64 * 100.0;
-            pb.finish_and_clear();
+        let columns = table_columns(matches)?;
+        let mut groups: Vec<(String, TableStats)> = groups.into_iter().collect();
+        groups.sort_by(|a, b| nanopore::group_order(&a.0, &b.0));
+        let mut all = empty;
+        for (group, stats) in groups {
+            writeln!(out, "{}\t{}", group, stats.row(infile, &columns))?;
+            all.merge(&stats);
+        }
+        return Ok(Done::Stats(Box::new(all))); // Go to the next file
 
-            // The header is now printed once before the loop (see top of main)
-            
-            println!(
-                "{}\t{}\t{}\t{}\t{}\t{}\t{:.2}\t{}\t{}\t{}\t{}\t{:.2}\t{:.2}",
-                infile,
-                reads,
-                bases,
-                num_n,
-                minlen,
-                maxlen,
-                mean_len,
-                quart1,
-                quart2,
-                quart3,
-                n50,
-                q20,
-                q30
-            );
-            continue; // Go to the next file
+    // case table (only runs if table is requested and none of the other single-task options were matched)
+    } else if matches.is_present("table") {
+        // normal case, output table
+        let mut stats = empty_table_stats(matches, offset)?;
+        let pb = spinner(progress);
+        let skip_filtered = matches.is_present("skip_filtered");
+        let mut filtered: i64 = 0;
+
+        while let Some(record) = records.iter_record()? {
+            if skip_filtered && illumina::is_filtered(record.head(), record.des()) {
+                filtered += 1;
+                continue;
+            }
+            stats.add(record.seq().as_bytes(), record.qual().as_bytes(), record.is_fasta());
+            let message = format!("Processed reads: {}", HumanCount(stats.reads as u64).to_string());
+            pb.set_message(message);
+        }
+        pb.finish_and_clear();
+        if skip_filtered {
+            eprintln!("{}: {} filtered reads skipped", infile, filtered);
+        }
+
+        // The header is now printed once before the loop (see top of main)
+        writeln!(out, "{}", stats.row(infile, &table_columns(matches)?))?;
+        return Ok(Done::Stats(Box::new(stats))); // Go to the next file
+    }
+    Ok(Done::Output)
+}
+
+// table columns from --columns (or the default ones) and --percentiles
+fn table_columns(matches: &ArgMatches) -> Result<Vec<String>, Error> {
+    let mut columns: Vec<String> = match matches.value_of("columns") {
+        Some(list) => list.split(',').map(|c| c.trim().to_string()).collect(),
+        None => stats::DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect(),
+    };
+    if let Some(list) = matches.value_of("percentiles") {
+        columns.extend(list.split(',').map(|p| format!("P{}", p.trim())));
+    }
+    stats::check_columns(&columns).map_err(Error::Argument)?;
+    if genome_size(matches)?.is_none() {
+        if columns.iter().any(|c| stats::is_genome_column(c)) {
+            return Err(Error::Argument("The columns NG<x>, LG<x> and depth need --genome_size".to_string()));
+        }
+    } else if !matches.is_present("columns") {
+        // added to the default columns, a --columns list is used as it is
+        columns.extend(["NG50", "LG50", "depth", "depth_10k"].iter().map(|c| c.to_string()));
+    }
+    Ok(columns)
+}
+
+// --genome_size in bases, e.g. from 4.6m
+fn genome_size(matches: &ArgMatches) -> Result<Option<i64>, Error> {
+    match matches.value_of("genome_size") {
+        Some(value) => match modules::parse_size(value) {
+            Some(size) => Ok(Some(size)),
+            None => Err(Error::Argument(format!("Invalid value '{}' for --genome_size, use e.g. 5000000, 4.6m or 3g", value))),
+        },
+        None => Ok(None),
+    }
+}
+
+// fields for the per read table
+fn read_fields(matches: &ArgMatches) -> Result<Vec<String>, Error> {
+    let fields: Vec<String> = matches
+        .value_of("fields")
+        .unwrap_or("id,length,gc,meanq")
+        .split(',')
+        .map(|f| f.trim().to_string())
+        .collect();
+    modules::check_read_fields(&fields).map_err(Error::Argument)?;
+    Ok(fields)
+}
+
+// min and max GC fraction from --filtergc, e.g. "0.3,0.6"
+fn gc_range(value: &str) -> Result<(f32, f32), Error> {
+    let invalid = || Error::Argument(format!("Invalid value '{}' for --filtergc, use min,max with 0.0 <= min <= max <= 1.0", value));
+    let bounds: Vec<f32> = value
+        .split(',')
+        .map(|x| x.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid())?;
+    match bounds[..] {
+        [min, max] if 0.0 <= min && min <= max && max <= 1.0 => Ok((min, max)),
+        _ => Err(invalid()),
+    }
+}
+
+// start of the run from --run_start, or the earliest read of all files if the times are needed
+fn run_start(matches: &ArgMatches, infiles: &[&str]) -> Result<Option<i64>, Error> {
+    if let Some(value) = matches.value_of("run_start") {
+        return match nanopore::parse_time(value.trim()) {
+            Some(time) => Ok(Some(time)),
+            None => Err(Error::Argument(format!("Invalid value '{}' for --run_start, use e.g. 2019-10-30T10:18:17Z", value))),
+        };
+    }
+    let hours = match matches.value_of("select") {
+        Some(select) => nanopore::Selection::uses_time(&nanopore::parse_conditions(select).map_err(Error::Argument)?),
+        None => false,
+    };
+    if matches.is_present("yield_time") || hours {
+        Ok(nanopore::run_start(infiles))
+    } else {
+        Ok(None)
+    }
+}
+
+// --yield_time in seconds
+fn yield_bin_size(matches: &ArgMatches) -> Result<i64, Error> {
+    let minutes: i64 = parse_value(matches, "yield_time")?.unwrap();
+    if minutes < 1 {
+        return Err(Error::Argument("The bin size for --yield_time should be at least 1 minute".to_string()));
+    }
+    Ok(minutes * 60)
+}
+
+// one row per bin from the run start (or the first bin before it) to the last bin, also the bins without reads
+fn write_yield(name: &str, bins: &BTreeMap<i64, nanopore::YieldBin>, matches: &ArgMatches) -> Result<(), Error> {
+    let size = yield_bin_size(matches)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if let (Some(&first), Some(&end)) = (bins.keys().next(), bins.keys().next_back()) {
+        let mut cumulative = nanopore::YieldBin::default();
+        let empty = nanopore::YieldBin::default();
+        for bin in first.min(0)..=end {
+            let current = bins.get(&bin).unwrap_or(&empty);
+            cumulative.merge(current);
+            let hours = (bin * size) as f64 / 3600.0;
+            writeln!(out, "{}\t{:.2}\t{}\t{}", name, hours, current.columns(), cumulative.columns())?;
         }
     }
+    Ok(())
+}
+
+fn watch_dirs(matches: &ArgMatches, dirs: &[&str], threads: usize, keep_going: bool) -> Result<(), Error> {
+    let watch = watch::Watch {
+        dirs: dirs.to_vec(),
+        interval: Duration::from_secs(parse_value(matches, "interval")?.unwrap_or(60)),
+        idle: parse_value(matches, "idle")?.map(Duration::from_secs),
+        stats_file: matches.value_of("stats_file"),
+        columns: table_columns(matches)?,
+        header: !matches.is_present("skip_header"),
+    };
+    // the rows per file are not needed, only the stats
+    watch.run(keep_going, |file| match process_file(file, matches, &mut io::sink(), threads, false, None)? {
+        Done::Stats(stats) => Ok(Some(*stats)),
+        _ => Ok(None),
+    })
+}
+
+fn compare_files(matches: &ArgMatches, infiles: &[&str]) -> Result<(), Error> {
+    let (a, b) = match infiles {
+        [a, b] => (*a, *b),
+        _ => return Err(Error::Argument("--compare needs exactly two input files".to_string())),
+    };
+    let stats_a = empty_table_stats(matches, phred_offset(matches, a)?)?;
+    let stats_b = empty_table_stats(matches, phred_offset(matches, b)?)?;
+    let header = !matches.is_present("skip_header");
+    let stdout = io::stdout();
+    compare::compare((a, stats_a), (b, stats_b), &table_columns(matches)?, header, &mut stdout.lock())
+}
+
+// fractions for --nx, e.g. "0.1,0.5,0.9" or "all" for 0.01 to 1.0
+fn nx_fractions(value: &str) -> Result<Vec<f64>, Error> {
+    if value.trim() == "all" {
+        return Ok((1..=100).map(|x| x as f64 / 100.0).collect());
+    }
+    value
+        .split(',')
+        .map(|x| match x.trim().parse::<f64>() {
+            Ok(x) if x > 0.0 && x <= 1.0 => Ok(x),
+            Ok(_) => Err(Error::Argument("The NX value should be above 0.0 and at most 1.0".to_string())),
+            Err(_) => Err(Error::Argument(format!("Invalid value '{}' for --nx", x))),
+        })
+        .collect()
+}
+
+// TableStats for one file, the per read statistics that are not in the selected columns are skipped
+fn empty_table_stats(matches: &ArgMatches, phred_offset: u8) -> Result<TableStats, Error> {
+    let columns = table_columns(matches)?;
+    let mut stats = TableStats::new(phred_offset);
+    stats.complexity = columns.iter().any(|c| c.contains("complexity"));
+    stats.report = matches.is_present("html");
+    stats.genome_size = genome_size(matches)?;
+    Ok(stats)
+}
+
+// phred offset from --phred, "auto" looks at the first reads of the file
+fn phred_offset(matches: &ArgMatches, infile: &str) -> Result<u8, Error> {
+    match matches.value_of("phred").unwrap_or("33").trim() {
+        "33" => Ok(33),
+        "64" => Ok(64),
+        "auto" => Ok(modules::detect_phred_offset(infile, 1000)),
+        other => Err(Error::Argument(format!("The phred offset should be 33, 64 or auto, not '{}'", other))),
+    }
+}
+
+// several spinners from concurrent files would garble the terminal, so they can be hidden
+fn spinner(progress: bool) -> ProgressBar {
+    let pb = if progress {
+        ProgressBar::new_spinner()
+    } else {
+        ProgressBar::hidden()
+    };
+    pb.enable_steady_tick(Duration::from_millis(120));
+    pb
 }
 // END
\ No newline at end of file
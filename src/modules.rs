@@ -1,51 +1,9 @@
-// simple helper functions for calcuting mean, quartiles etc
-use rayon::prelude::*;
+// simple helper functions for counting bases, qualities etc. and writing records
+use bio::seq_analysis::gc::gc_content;
 use kseq::record::Fastx;
-pub fn mean(numbers: &[i64]) -> f64 {
-    numbers.par_iter().sum::<i64>() as f64 / numbers.len() as f64
-}
-
-pub fn quartiles(numbers: &mut [i64], q: i8) -> i64 {
-    numbers.sort_unstable();
-
-    match q {
-        1 => {
-            let index = numbers.len() / 4;
-            return numbers[index];
-        }
-        2 => {
-            let index = numbers.len() / 2;
-            return numbers[index];
-        }
-        3 => {
-            // avoid having to use f64
-            let index1 = numbers.len() / 4;
-            let index2 = numbers.len() / 2;
-            return numbers[index1 + index2];
-        }
-        _ => 42, //:)
-    }
-    // first quartile
-}
-
-pub fn get_nx(numbers: &mut [i64], fraction: f32) -> i64 {
-    numbers.sort_unstable();
-
-    // half of the bases
-    let halfsum = numbers.par_iter().sum::<i64>() as f32 * fraction; // f32 * f32
-
-    // cumsum of the sorted vector
-    let cumsum = numbers
-        .iter()
-        .scan(0, |sum, i| {
-            *sum += i;
-            Some(*sum)
-        })
-        .collect::<Vec<_>>();
-    let n50_index = cumsum.par_iter().position_first(|&x| x > halfsum as i64).unwrap();
-
-    numbers[n50_index]
-}
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
 
 // get number of bases with q >= value
 pub fn get_qual_bases(q: &[u8], qx: u8) -> i64 {
@@ -69,30 +27,249 @@ pub fn get_n_bases(seq: &[u8]) -> i32 {
     n
 }
 
+// get number of IUPAC ambiguity codes (N, R, Y, K, M, S, W, B, D, H, V)
+pub fn get_ambiguous_bases(seq: &[u8]) -> i32 {
+    let mut n = 0;
+    for s in seq {
+        if matches!(s.to_ascii_uppercase(), b'N' | b'R' | b'Y' | b'K' | b'M' | b'S' | b'W' | b'B' | b'D' | b'H' | b'V') {
+            n += 1;
+        }
+    }
+    n
+}
+
+// sequence complexity as the Shannon entropy of the trinucleotides, scaled to 0..1 by the highest
+// entropy possible for the number of trinucleotides. Homopolymers and short repeats get values
+// close to 0, random sequence close to 1. Trinucleotides with other bases than ACGT are skipped
+pub fn get_complexity(seq: &[u8]) -> f64 {
+    let mut counts = [0u32; 64];
+    let mut kmer = 0usize;
+    let mut valid = 0;
+    let mut n = 0;
+    for s in seq {
+        let code = match s {
+            b'A' | b'a' => 0,
+            b'C' | b'c' => 1,
+            b'G' | b'g' => 2,
+            b'T' | b't' => 3,
+            _ => {
+                valid = 0;
+                continue;
+            }
+        };
+        kmer = ((kmer << 2) | code) & 63;
+        valid += 1;
+        if valid >= 3 {
+            counts[kmer] += 1;
+            n += 1;
+        }
+    }
+    if n < 2 {
+        return 0.0;
+    }
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / n as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum();
+    entropy / (n.min(64) as f64).log2()
+}
+
+// 128 bit hash of a read id (or any other bytes), to keep many ids in memory
+pub fn id_hash(id: &[u8]) -> u128 {
+    let mut h1 = DefaultHasher::new();
+    let mut h2 = DefaultHasher::new();
+    id.hash(&mut h1);
+    (id, 1u8).hash(&mut h2);
+    (h1.finish() as u128) << 64 | h2.finish() as u128
+}
+
+// number of N bases, G and C bases and N runs (stretches of one or more N, e.g. scaffold gaps in
+// an assembly), in one pass over the sequence for the table
+pub fn get_base_counts(seq: &[u8]) -> (i64, i64, i64) {
+    let (mut n, mut gc, mut n_runs) = (0, 0, 0);
+    let mut in_run = false;
+    for s in seq {
+        let is_n = matches!(s, b'N' | b'n');
+        if is_n {
+            n += 1;
+            if !in_run {
+                n_runs += 1;
+            }
+        } else if matches!(s, b'G' | b'C' | b'g' | b'c') {
+            gc += 1;
+        }
+        in_run = is_n;
+    }
+    (n, gc, n_runs)
+}
+
+// a size like 5000000, 4.6m or 3g (k, m and g are powers of 1000), None if it is not one
+pub fn parse_size(s: &str) -> Option<i64> {
+    let s = s.trim().to_lowercase();
+    let (number, factor) = match s.chars().last()? {
+        'k' => (&s[..s.len() - 1], 1e3),
+        'm' => (&s[..s.len() - 1], 1e6),
+        'g' => (&s[..s.len() - 1], 1e9),
+        _ => (&s[..], 1.0),
+    };
+    let size = number.parse::<f64>().ok()? * factor;
+    if size.is_finite() && size >= 1.0 { Some(size.round() as i64) } else { None }
+}
+
 // to get mean of q scores from a record - first convert to prob, calc mean, then back to phred
 // this fn reads phred and converts to probs and returns their sum
 //
 // see how seqkit is doing it
 // https://github.com/shenwei356/bio/blob/1886d4a9eab7315f6f445595acbdc7bc3edf0e08/seq/seq.go#L727
 
-pub fn qscore_probs(q: &[u8]) -> f32 {
+pub fn qscore_probs(q: &[u8], offset: u8) -> f32 {
     let mut qprob_sum = 0.0;
     for &item in q.iter() {
-        let phred = *&item as f32 - 33.0;
+        let phred = item as f32 - offset as f32;
         let prob = 10.0_f32.powf(-phred / 10.0);
         qprob_sum += prob
     }
     qprob_sum
 }
 
-pub fn write_fastq(rec: Fastx<'_>) {
-    println!(
+// fields of the per read table, first<k> and last<k> (e.g. first10) are the first/last k bases
+pub const READ_FIELDS: [&str; 9] = ["file", "id", "description", "length", "gc", "meanq", "ee", "n_count", "complexity"];
+
+pub fn check_read_fields(fields: &[String]) -> Result<(), String> {
+    for f in fields {
+        let bases = f
+            .strip_prefix("first")
+            .or_else(|| f.strip_prefix("last"))
+            .map(|k| k.parse::<usize>().is_ok());
+        if !READ_FIELDS.contains(&f.as_str()) && bases != Some(true) {
+            return Err(format!(
+                "Unknown per read field '{}', use one of {}, first<k> or last<k>",
+                f,
+                READ_FIELDS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+// value of one field of the per read table, "file" is handled by the caller
+pub fn read_field(rec: &Fastx<'_>, field: &str, offset: u8) -> String {
+    let seq = rec.seq();
+    match field {
+        "id" => rec.head().to_string(),
+        "description" => rec.des().trim().to_string(),
+        "length" => rec.len().to_string(),
+        "gc" => format!("{:.4}", gc_content(seq.as_bytes())),
+        // fasta records have no qualities
+        "meanq" | "ee" if rec.is_fasta() => "NA".to_string(),
+        "meanq" => format!("{:.4}", -10.0 * (qscore_probs(rec.qual().as_bytes(), offset) / rec.len() as f32).log10()),
+        // expected errors, the sum of the error probabilities
+        "ee" => format!("{:.4}", qscore_probs(rec.qual().as_bytes(), offset)),
+        "n_count" => get_n_bases(seq.as_bytes()).to_string(),
+        "complexity" => format!("{:.4}", get_complexity(seq.as_bytes())),
+        _ => match field.strip_prefix("first") {
+            Some(k) => seq[..k.parse::<usize>().unwrap().min(seq.len())].to_string(),
+            None => {
+                let k = field["last".len()..].parse::<usize>().unwrap().min(seq.len());
+                seq[seq.len() - k..].to_string()
+            }
+        },
+    }
+}
+
+// guess the phred offset from the quality range of the first records: Phred+64 (Illumina 1.3 to 1.7)
+// has no quality characters below '@', while Phred+33 data practically always has some
+pub fn detect_phred_offset(infile: &str, nrecords: usize) -> u8 {
+    // stdin can not be read twice
+    if infile == "-" {
+        eprintln!("Can not detect the phred offset on stdin, using 33");
+        return 33;
+    }
+    let mut records = match kseq::parse_path(infile) {
+        Ok(records) => records,
+        Err(_) => return 33, // the error is reported when the file is read for real
+    };
+    let mut minq = u8::MAX;
+    let mut n = 0;
+    while let Ok(Some(record)) = records.iter_record() {
+        if let Some(&q) = record.qual().as_bytes().iter().min() {
+            minq = minq.min(q);
+        }
+        n += 1;
+        if n == nrecords {
+            break;
+        }
+    }
+    if minq != u8::MAX && minq >= 64 {
+        64
+    } else {
+        33
+    }
+}
+
+// write a fastq record with new qualities, e.g. converted to Phred+33
+pub fn write_fastq_qual(rec: Fastx<'_>, qual: &[u8], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "{} {}\n{}\n+", "@".to_string() + rec.head(), rec.des(), rec.seq())?;
+    out.write_all(qual)?;
+    out.write_all(b"\n")
+}
+
+// fasta records (no quality) are written back as fasta
+pub fn write_fastq(rec: Fastx<'_>, out: &mut dyn Write) -> io::Result<()> {
+    if rec.is_fasta() {
+        return writeln!(out, "{} {}\n{}", ">".to_string() + rec.head(), rec.des(), rec.seq());
+    }
+    writeln!(
+        out,
         "{} {}\n{}\n{}\n{}", 
         "@".to_string() + rec.head(), rec.des(), 
         rec.seq(), 
         "+", 
         rec.qual()
-    );
+    )
+}
+
+// write the bases from..to of a record, fasta records are written back as fasta like in write_fastq
+pub fn write_trimmed(rec: Fastx<'_>, from: usize, to: usize, out: &mut dyn Write) -> io::Result<()> {
+    if rec.is_fasta() {
+        return writeln!(out, "{} {}\n{}", ">".to_string() + rec.head(), rec.des(), &rec.seq()[from..to]);
+    }
+    writeln!(
+        out,
+        "{} {}\n{}\n{}\n{}",
+        "@".to_string() + rec.head(), rec.des(),
+        &rec.seq()[from..to],
+        "+",
+        &rec.qual()[from..to]
+    )
+}
+
+// write a record as fasta, wrapping the sequence to width bases per line (0 means no wrapping)
+pub fn write_fasta(rec: Fastx<'_>, width: usize, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "{} {}", ">".to_string() + rec.head(), rec.des())?;
+    if width == 0 {
+        return writeln!(out, "{}", rec.seq());
+    }
+    for line in rec.seq().as_bytes().chunks(width) {
+        out.write_all(line)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+// write a fasta record as fastq, all bases get the same quality character
+pub fn write_fasta_as_fastq(rec: Fastx<'_>, qchar: char, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "{} {}\n{}\n+\n{}",
+        "@".to_string() + rec.head(), rec.des(),
+        rec.seq(),
+        qchar.to_string().repeat(rec.len())
+    )
 }
 // get geometric mean from phred scores
 // pub fn phred_gm(q: &[u8]) -> f64 {
@@ -0,0 +1,251 @@
+// Oxford Nanopore read headers
+//
+// the description of ONT reads is a list of key=value tags, e.g.
+// @id runid=... read=12 ch=100 start_time=2021-06-01T10:00:00Z flow_cell_id=... barcode=barcode01
+// basecall_model_version_id=...
+use crate::stats::LengthCounts;
+
+// value of the tag key in the description, None if the read does not have it
+pub fn tag<'a>(des: &'a str, key: &str) -> Option<&'a str> {
+    des.split_whitespace()
+        .filter_map(|t| t.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+// the tag keys that can be given with their long names
+pub fn tag_key(name: &str) -> &str {
+    match name {
+        "channel" => "ch",
+        "run" => "runid",
+        "read_number" => "read",
+        "model" => "basecall_model_version_id",
+        _ => name,
+    }
+}
+
+// order of the groups in the output, numbers (channels) numerically and the rest alphabetically
+pub fn group_order(a: &str, b: &str) -> std::cmp::Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(x), Ok(y)) => x.cmp(&y),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        _ => a.cmp(b),
+    }
+}
+
+// channels of the flow cells: Flongle, MinION/GridION and PromethION
+const FLOWCELL_CHANNELS: [usize; 3] = [126, 512, 3000];
+
+// channels of the smallest flow cell with this channel, the largest flow cell if it is above all of them
+pub fn flowcell_channels(max_channel: usize) -> usize {
+    FLOWCELL_CHANNELS.iter().copied().find(|&c| c >= max_channel).unwrap_or(FLOWCELL_CHANNELS[2])
+}
+
+// seconds since 1970 of a start_time like 2019-10-30T10:18:17Z. Fractions of a second are dropped,
+// offsets like +01:00 are taken into account
+pub fn parse_time(s: &str) -> Option<i64> {
+    let (date, time) = s.split_once('T')?;
+    let mut ymd = date.splitn(3, '-').map(|x| x.parse::<i64>().ok());
+    let (year, month, day) = (ymd.next()??, ymd.next()??, ymd.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let zone_start = time.find(['Z', '+', '-']).unwrap_or(time.len());
+    let (clock, zone) = time.split_at(zone_start);
+    let mut hms = clock.splitn(3, ':');
+    let hours: i64 = hms.next()?.parse().ok()?;
+    let minutes: i64 = hms.next()?.parse().ok()?;
+    let seconds: f64 = hms.next()?.parse().ok()?;
+    let offset = match zone {
+        "" | "Z" => 0,
+        _ => {
+            let (h, m) = zone[1..].split_once(':').unwrap_or((&zone[1..], "0"));
+            let offset = h.parse::<i64>().ok()? * 3600 + m.parse::<i64>().ok()? * 60;
+            if zone.starts_with('-') { -offset } else { offset }
+        }
+    };
+    Some(days_from_civil(year, month, day) * 86400 + hours * 3600 + minutes * 60 + seconds as i64 - offset)
+}
+
+// days since 1970-01-01 of a date in the proleptic Gregorian calendar
+// see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// reads of one time bin for the yield over time
+#[derive(Clone)]
+pub struct YieldBin {
+    pub reads: i64,
+    pub bases: i64,
+    // sum of the base error probabilities, for the mean Q over all bases
+    pub error_sum: f64,
+    pub fasta: bool,
+    pub lengths: LengthCounts,
+}
+
+// a long run has thousands of bins, their lengths are kept in a map only
+impl Default for YieldBin {
+    fn default() -> Self {
+        YieldBin {
+            reads: 0,
+            bases: 0,
+            error_sum: 0.0,
+            fasta: false,
+            lengths: LengthCounts::sparse(),
+        }
+    }
+}
+
+impl YieldBin {
+    pub fn add(&mut self, len: i64, error_sum: f64, fasta: bool) {
+        self.reads += 1;
+        self.bases += len;
+        self.error_sum += error_sum;
+        self.fasta |= fasta;
+        self.lengths.add(len);
+    }
+
+    pub fn merge(&mut self, other: &YieldBin) {
+        self.reads += other.reads;
+        self.bases += other.bases;
+        self.error_sum += other.error_sum;
+        self.fasta |= other.fasta;
+        self.lengths.merge(&other.lengths);
+    }
+
+    // reads, bases, mean Q and N50, tab separated
+    pub fn columns(&self) -> String {
+        let meanq = if self.fasta || self.bases == 0 {
+            "NA".to_string()
+        } else {
+            format!("{:.2}", -10.0 * (self.error_sum / self.bases as f64).log10())
+        };
+        let n50 = if self.reads == 0 { 0 } else { self.lengths.nx(&[0.5])[0].0 };
+        format!("{}\t{}\t{}\t{}", self.reads, self.bases, meanq, n50)
+    }
+}
+
+// start of the run as the earliest start_time over all input files (a run is written as many
+// files), this is an extra pass over the inputs. Stdin can not be read twice, there the first read
+// with a start_time is taken as the start (None here)
+pub fn run_start(infiles: &[&str]) -> Option<i64> {
+    if infiles.contains(&"-") {
+        eprintln!("Can not find the run start on stdin, the times are relative to the first read (see --run_start)");
+        return None;
+    }
+    let mut start: Option<i64> = None;
+    for infile in infiles {
+        // read errors are reported when the file is read for real
+        let mut records = match kseq::parse_path(*infile) {
+            Ok(records) => records,
+            Err(_) => continue,
+        };
+        while let Ok(Some(record)) = records.iter_record() {
+            if let Some(time) = tag(record.des(), "start_time").and_then(parse_time) {
+                start = Some(start.map_or(time, |s| s.min(time)));
+            }
+        }
+    }
+    start
+}
+
+enum Value {
+    Exact(String),
+    // inclusive numeric range, e.g. ch=1-100
+    Range(f64, f64),
+}
+
+// one condition of --select, e.g. barcode=barcode03, ch!=1-100 or hours=0-24. A condition can have
+// several values separated by |, the tag has to match one of them (or none for !=)
+pub struct Condition {
+    key: String,
+    exclude: bool,
+    values: Vec<Value>,
+}
+
+// hours is not a tag but the start_time relative to the start of the run
+pub const HOURS: &str = "hours";
+
+impl Condition {
+    // value is the tag value and number the same as a number, None if the tag is missing or not a
+    // number. The hours condition has only a number
+    fn matches(&self, value: Option<&str>, number: Option<f64>) -> bool {
+        let found = self.values.iter().any(|v| match v {
+            Value::Exact(x) => value == Some(x.as_str()),
+            Value::Range(from, to) => number.is_some_and(|x| x >= *from && x <= *to),
+        });
+        found != self.exclude
+    }
+}
+
+pub fn parse_conditions(s: &str) -> Result<Vec<Condition>, String> {
+    let mut conditions = Vec::new();
+    for c in s.split(',') {
+        let (key, values, exclude) = match c.split_once("!=") {
+            Some((key, values)) => (key, values, true),
+            None => match c.split_once('=') {
+                Some((key, values)) => (key, values, false),
+                None => return Err(format!("Invalid condition '{}', use key=value, key!=value or key=from-to", c)),
+            },
+        };
+        let key = tag_key(key.trim());
+        let values = values
+            .split('|')
+            .map(|v| {
+                let range = v.split_once('-').and_then(|(a, b)| Some((a.parse::<f64>().ok()?, b.parse::<f64>().ok()?)));
+                match range {
+                    Some((from, to)) => Ok(Value::Range(from, to)),
+                    // hours has only numbers, hours=24 is the first 24 hours
+                    None if key == HOURS => match v.parse::<f64>() {
+                        Ok(to) => Ok(Value::Range(0.0, to)),
+                        Err(_) => Err(format!("Invalid condition '{}', use hours=from-to or hours=<hours>", c)),
+                    },
+                    None => Ok(Value::Exact(v.to_string())),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        conditions.push(Condition { key: key.to_string(), exclude, values });
+    }
+    Ok(conditions)
+}
+
+// selects reads by their tags, all conditions have to match
+pub struct Selection {
+    conditions: Vec<Condition>,
+    run_start: Option<i64>,
+}
+
+impl Selection {
+    pub fn new(conditions: Vec<Condition>, run_start: Option<i64>) -> Self {
+        Selection { conditions, run_start }
+    }
+
+    pub fn uses_time(conditions: &[Condition]) -> bool {
+        conditions.iter().any(|c| c.key == HOURS)
+    }
+
+    pub fn matches(&mut self, des: &str) -> bool {
+        let run_start = &mut self.run_start;
+        for c in &self.conditions {
+            let found = if c.key == HOURS {
+                let time = tag(des, "start_time").and_then(parse_time);
+                let hours = time.map(|t| (t - *run_start.get_or_insert(t)) as f64 / 3600.0);
+                c.matches(None, hours)
+            } else {
+                let value = tag(des, &c.key);
+                c.matches(value, value.and_then(|v| v.parse::<f64>().ok()))
+            };
+            if !found {
+                return false;
+            }
+        }
+        true
+    }
+}
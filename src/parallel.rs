@@ -0,0 +1,110 @@
+// run a task on several input files concurrently, while keeping the output in input order
+//
+// every file gets its own bounded channel, the worker sends its output in chunks and the main thread
+// prints the channels one after the other. Files are started in input order, so the file being printed
+// is always running or done and a worker can only wait on files that come before it.
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+const CHUNK_SIZE: usize = 1 << 16;
+// how many chunks a worker can get ahead of the printing thread
+const CHUNKS_IN_FLIGHT: usize = 64;
+
+// a writer that collects output and sends it to the printing thread in chunks
+pub struct ChunkWriter {
+    buf: Vec<u8>,
+    tx: SyncSender<Vec<u8>>,
+}
+
+impl ChunkWriter {
+    pub fn new(tx: SyncSender<Vec<u8>>) -> Self {
+        ChunkWriter {
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            tx,
+        }
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+            self.tx
+                .send(chunk)
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "output was closed"))?;
+        }
+        Ok(())
+    }
+}
+
+// the results of the task are passed to handle in input order too, as soon as the output of the file
+// is printed. Unless keep_going, this stops after the first failed file, the files that are running
+// are finished but no new ones are started and the results of the later files are dropped
+pub fn run_ordered<F, H, T, E>(infiles: &[&str], threads: usize, keep_going: bool, task: F, mut handle: H)
+where
+    F: Fn(&str, &mut dyn Write) -> Result<T, E> + Sync,
+    H: FnMut(&str, Result<T, E>),
+    T: Send,
+    E: Send + From<io::Error>,
+{
+    let mut senders = Vec::new();
+    let mut receivers = Vec::new();
+    for _ in infiles {
+        let (tx, rx) = sync_channel(CHUNKS_IN_FLIGHT);
+        senders.push(Mutex::new(Some(tx)));
+        receivers.push(rx);
+    }
+    let results: Vec<Mutex<Option<Result<T, E>>>> = infiles.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    // set on an early return, scope still waits for the workers
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        for _ in 0..threads.min(infiles.len()) {
+            s.spawn(|| loop {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= infiles.len() {
+                    break;
+                }
+                let tx = senders[i].lock().unwrap().take().unwrap();
+                let mut writer = ChunkWriter::new(tx);
+                let result = task(infiles[i], &mut writer).and_then(|r| writer.flush().map(|_| r).map_err(E::from));
+                *results[i].lock().unwrap() = Some(result);
+                // dropping the writer closes the channel, this is how the printing thread knows the file is done
+            });
+        }
+
+        let stdout = io::stdout();
+        for (i, rx) in receivers.into_iter().enumerate() {
+            for chunk in rx {
+                if let Err(e) = stdout.lock().write_all(&chunk) {
+                    stop.store(true, Ordering::SeqCst);
+                    handle(infiles[i], Err(E::from(e)));
+                    return;
+                }
+            }
+            // the channel is closed only after the result was stored
+            let result = results[i].lock().unwrap().take().unwrap();
+            let failed = result.is_err();
+            handle(infiles[i], result);
+            if failed && !keep_going {
+                stop.store(true, Ordering::SeqCst);
+                return;
+            }
+        }
+    })
+}
@@ -0,0 +1,277 @@
+// multi-threaded table stats for one (big) file
+//
+// a reader thread decompresses the input (BGZF blocks are inflated in parallel), the calling thread
+// parses the records and sends them in batches to worker threads, every worker fills its own
+// TableStats and these are merged at the end. threads is the upper limit for all of these together
+use crate::error::{self, Error};
+use crate::stats::TableStats;
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use indicatif::{HumanCount, ProgressBar};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+const CHUNK_SIZE: usize = 1 << 20;
+const BATCH_RECORDS: usize = 4096;
+
+// records of a batch are copied back to back in one buffer
+struct Batch {
+    data: Vec<u8>,
+    // seq start, seq end (= qual start), qual end, is fasta
+    records: Vec<(usize, usize, usize, bool)>,
+}
+
+impl Batch {
+    fn new() -> Self {
+        Batch {
+            data: Vec::with_capacity(BATCH_RECORDS * 256),
+            records: Vec::with_capacity(BATCH_RECORDS),
+        }
+    }
+
+    fn add_to(&self, stats: &mut TableStats) {
+        for &(seq, qual, end, fasta) in &self.records {
+            stats.add(&self.data[seq..qual], &self.data[qual..end], fasta);
+        }
+    }
+}
+
+// empty is the (still empty) TableStats with the settings every worker starts from
+pub fn table_stats(infile: &str, threads: usize, empty: &TableStats, pb: &ProgressBar) -> Result<TableStats, Error> {
+    // one thread parses, BGZF input gets half of the rest for inflating
+    let reader = spawn_reader(infile, 1 + threads.saturating_sub(2) / 2).map_err(Error::Input)?;
+    let workers = threads.saturating_sub(1 + reader.threads);
+    let mut records = kseq::parse_reader(reader).map_err(error::input_error)?;
+
+    // no threads left for workers, the parsing thread does the stats too
+    if workers == 0 {
+        let mut stats = empty.clone();
+        send_batches(&mut records, |batch| batch.add_to(&mut stats), pb)?;
+        return Ok(stats);
+    }
+
+    let (tx, rx) = sync_channel::<Batch>(workers * 2);
+    let rx = Mutex::new(rx);
+
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut stats = empty.clone();
+                    loop {
+                        // the guard is dropped here, before the batch is processed
+                        let batch = match rx.lock().unwrap().recv() {
+                            Ok(batch) => batch,
+                            Err(_) => break,
+                        };
+                        batch.add_to(&mut stats);
+                    }
+                    stats
+                })
+            })
+            .collect();
+
+        // tx is moved, so the channel is closed (and the workers stop) also when parsing fails. The
+        // workers only stop when the channel is closed, so sending can not fail
+        let parsed = send_batches(&mut records, move |batch| tx.send(batch).unwrap(), pb);
+
+        let mut stats = empty.clone();
+        for handle in handles {
+            stats.merge(&handle.join().unwrap());
+        }
+        parsed.map(|_| stats)
+    })
+}
+
+fn send_batches<F: FnMut(Batch)>(records: &mut kseq::Paths, mut send: F, pb: &ProgressBar) -> Result<(), Error> {
+    let mut reads: u64 = 0;
+    let mut batch = Batch::new();
+    while let Some(record) = records.iter_record()? {
+        let seq = batch.data.len();
+        batch.data.extend_from_slice(record.seq().as_bytes());
+        let qual = batch.data.len();
+        batch.data.extend_from_slice(record.qual().as_bytes());
+        batch.records.push((seq, qual, batch.data.len(), record.is_fasta()));
+
+        if batch.records.len() == BATCH_RECORDS {
+            reads += BATCH_RECORDS as u64;
+            pb.set_message(format!("Processed reads: {}", HumanCount(reads)));
+            send(std::mem::replace(&mut batch, Batch::new()));
+        }
+    }
+    send(batch);
+    Ok(())
+}
+
+// a Read over the decompressed chunks coming from the reader thread
+pub struct ChannelReader {
+    // threads used for decompressing, only BGZF input uses more than one
+    pub threads: usize,
+    rx: Receiver<io::Result<Vec<u8>>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.buf = chunk?;
+                    self.pos = 0;
+                }
+                // the reader thread is done
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// open the input and start decompressing it on its own thread, BGZF blocks are inflated by up to
+// threads threads (the reader thread only waits for them)
+pub fn spawn_reader(infile: &str, threads: usize) -> io::Result<ChannelReader> {
+    let threads = threads.max(1);
+    let input: Box<dyn Read + Send> = if infile == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(File::open(infile)?)
+    };
+    let mut input = BufReader::with_capacity(CHUNK_SIZE, input);
+    let (tx, rx) = sync_channel(threads * 2);
+
+    let start = input.fill_buf()?;
+    let bgzf = is_bgzf(start);
+    if bgzf {
+        thread::spawn(move || read_bgzf(input, threads, tx));
+    } else if start.starts_with(&[0x1f, 0x8b]) {
+        thread::spawn(move || read_chunks(MultiGzDecoder::new(input), tx));
+    } else {
+        thread::spawn(move || read_chunks(input, tx));
+    }
+
+    Ok(ChannelReader {
+        threads: if bgzf { threads } else { 1 },
+        rx,
+        buf: Vec::new(),
+        pos: 0,
+    })
+}
+
+// BGZF is gzip with an extra field 'BC' holding the block size, as written by bgzip and samtools
+fn is_bgzf(start: &[u8]) -> bool {
+    start.len() >= 16
+        && start[..3] == [0x1f, 0x8b, 8]
+        && start[3] & 4 != 0
+        && start[12..14] == [b'B', b'C']
+}
+
+fn read_chunks<R: Read>(mut input: R, tx: SyncSender<io::Result<Vec<u8>>>) {
+    loop {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let result = match input.read(&mut chunk) {
+            Ok(0) => return,
+            Ok(n) => {
+                chunk.truncate(n);
+                Ok(chunk)
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => Err(e),
+        };
+        let failed = result.is_err();
+        // the receiving side hung up or there was an error, stop reading in both cases
+        if tx.send(result).is_err() || failed {
+            return;
+        }
+    }
+}
+
+// read as many blocks as there are threads (times 4) and inflate them in parallel
+fn read_bgzf<R: BufRead>(mut input: R, threads: usize, tx: SyncSender<io::Result<Vec<u8>>>) {
+    let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool,
+        Err(e) => {
+            let _ = tx.send(Err(io::Error::other(e)));
+            return;
+        }
+    };
+    loop {
+        let mut blocks = Vec::with_capacity(threads * 4);
+        let mut result = Ok(());
+        while blocks.len() < threads * 4 {
+            match read_bgzf_block(&mut input) {
+                Ok(Some(block)) => blocks.push(block),
+                Ok(None) => break,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        let done = blocks.len() < threads * 4;
+
+        let inflated: Vec<io::Result<Vec<u8>>> =
+            pool.install(|| blocks.par_iter().map(|block| inflate_bgzf_block(block)).collect());
+        for chunk in inflated {
+            let failed = chunk.is_err();
+            if tx.send(chunk).is_err() || failed {
+                return;
+            }
+        }
+        if let Err(e) = result {
+            let _ = tx.send(Err(e));
+            return;
+        }
+        if done {
+            return;
+        }
+    }
+}
+
+// one raw block (compressed data and the 8 byte trailer), None at the end of the input
+fn read_bgzf_block<R: BufRead>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    if input.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let mut header = [0u8; 12];
+    input.read_exact(&mut header)?;
+    if header[..2] != [0x1f, 0x8b] || header[3] & 4 == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a BGZF block"));
+    }
+    let xlen = u16::from_le_bytes([header[10], header[11]]) as usize;
+    let mut extra = vec![0; xlen];
+    input.read_exact(&mut extra)?;
+
+    // look for the BC subfield with the total block size - 1
+    let mut bsize = None;
+    let mut i = 0;
+    while i + 4 <= xlen {
+        let slen = u16::from_le_bytes([extra[i + 2], extra[i + 3]]) as usize;
+        if extra[i] == b'B' && extra[i + 1] == b'C' && slen == 2 && i + 6 <= xlen {
+            bsize = Some(u16::from_le_bytes([extra[i + 4], extra[i + 5]]) as usize);
+        }
+        i += 4 + slen;
+    }
+    let bsize = bsize.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "BGZF block without size"))?;
+    if bsize + 1 < 12 + xlen + 8 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "BGZF block size is too small"));
+    }
+
+    let mut block = vec![0; bsize + 1 - 12 - xlen];
+    input.read_exact(&mut block)?;
+    Ok(Some(block))
+}
+
+fn inflate_bgzf_block(block: &[u8]) -> io::Result<Vec<u8>> {
+    let (data, trailer) = block.split_at(block.len() - 8);
+    let isize = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) as usize;
+    let mut out = Vec::with_capacity(isize);
+    DeflateDecoder::new(data).read_to_end(&mut out)?;
+    Ok(out)
+}
@@ -0,0 +1,197 @@
+// self-contained html report for the table mode
+//
+// one section per input file with the table statistics and inline SVG plots of the read length
+// histogram, the 'mean' read phred score histogram and the quality along the reads. Everything
+// is in the one file, no scripts, fonts or stylesheets are loaded
+use crate::error::Error;
+use crate::stats::TableStats;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 240.0;
+const LEFT: f64 = 70.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 30.0;
+const BOTTOM: f64 = 45.0;
+// positions along the reads are averaged to at most this many points
+const MAX_POINTS: usize = 500;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; }
+td { padding: 2px 12px; border-bottom: 1px solid #ddd; }
+td:last-child { text-align: right; }
+section { margin-bottom: 3em; }
+svg { display: block; margin: 1em 0; }
+svg text { font-size: 11px; }
+.bar { fill: #4682b4; }
+.line { fill: none; stroke: #4682b4; stroke-width: 1.5; }
+.axis { stroke: #444; }";
+
+// sections are (name, stats), e.g. one per input file and the total
+pub fn write_html(path: &str, sections: &[(&str, &TableStats)], columns: &[String]) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>faster report</title>")?;
+    writeln!(out, "<style>\n{}\n</style>\n</head>\n<body>\n<h1>faster report</h1>\n<ul>", STYLE)?;
+    for (i, (name, _)) in sections.iter().enumerate() {
+        writeln!(out, "<li><a href=\"#s{}\">{}</a></li>", i, escape(name))?;
+    }
+    writeln!(out, "</ul>")?;
+    for (i, (name, stats)) in sections.iter().enumerate() {
+        writeln!(out, "<section id=\"s{}\">\n<h2>{}</h2>", i, escape(name))?;
+        write!(out, "{}", stats_table(name, stats, columns))?;
+        writeln!(out, "{}", length_histogram(stats))?;
+        if stats.fasta || stats.qual_hist.is_empty() {
+            writeln!(out, "<p>No quality plots, the reads have no qualities (fasta).</p>")?;
+        } else {
+            writeln!(out, "{}", qual_histogram(stats))?;
+            writeln!(out, "{}", position_quality(stats))?;
+        }
+        writeln!(out, "</section>")?;
+    }
+    writeln!(out, "</body>\n</html>")?;
+    out.flush()?;
+    Ok(())
+}
+
+// the table row as column - value pairs
+fn stats_table(name: &str, stats: &TableStats, columns: &[String]) -> String {
+    let mut html = String::from("<table>\n");
+    for (column, value) in columns.iter().zip(stats.row(name, columns).split('\t')) {
+        let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", escape(column), escape(value));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+// reads per length on a log scale, 10 bins per power of ten
+fn length_histogram(stats: &TableStats) -> String {
+    let bin = |len: i64| (10.0 * (len.max(1) as f64).log10()).floor() as usize;
+    let mut counts: Vec<f64> = Vec::new();
+    for (len, count) in stats.lengths.iter() {
+        let b = bin(len);
+        if b >= counts.len() {
+            counts.resize(b + 1, 0.0);
+        }
+        counts[b] += count as f64;
+    }
+    let first = counts.iter().position(|&c| c > 0.0).unwrap_or(0);
+    let labels = (first..counts.len())
+        .filter(|b| b % 10 == 0)
+        .map(|b| (b - first, format!("{:.0}", 10f64.powi(b as i32 / 10))))
+        .collect::<Vec<_>>();
+    bar_chart("Read length", "read length (log scale)", "reads", &counts[first..], &labels)
+}
+
+fn qual_histogram(stats: &TableStats) -> String {
+    let counts: Vec<f64> = stats.qual_hist.iter().map(|&c| c as f64).collect();
+    let labels = (0..counts.len())
+        .filter(|q| q % 5 == 0)
+        .map(|q| (q, q.to_string()))
+        .collect::<Vec<_>>();
+    bar_chart("'Mean' read phred score", "phred score", "reads", &counts, &labels)
+}
+
+// mean phred score (from the mean error probability) of the reads covering a position
+fn position_quality(stats: &TableStats) -> String {
+    // reads with a length > position
+    let mut covering = vec![0i64; stats.pos_error.len() + 1];
+    for (len, count) in stats.lengths.iter() {
+        covering[(len as usize).min(stats.pos_error.len())] += count;
+    }
+    for i in (0..stats.pos_error.len()).rev() {
+        covering[i] += covering[i + 1];
+    }
+    let step = stats.pos_error.len().div_ceil(MAX_POINTS);
+    let points: Vec<(f64, f64)> = (0..stats.pos_error.len())
+        .step_by(step.max(1))
+        .map(|start| {
+            let end = (start + step).min(stats.pos_error.len());
+            let error: f64 = stats.pos_error[start..end].iter().sum();
+            let reads: i64 = covering[start + 1..=end].iter().sum();
+            ((start + 1) as f64, -10.0 * (error / reads as f64).log10())
+        })
+        .collect();
+    line_chart("Quality along the reads", "position", "mean phred score", &points)
+}
+
+// svg element with the axes, the title and the y axis ticks, without the closing tag
+fn frame(title: &str, xlabel: &str, ylabel: &str, ymax: f64) -> String {
+    let bottom = HEIGHT - BOTTOM;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = WIDTH,
+        h = HEIGHT
+    );
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"18\" text-anchor=\"middle\" font-weight=\"bold\">{}</text>", WIDTH / 2.0, escape(title));
+    let _ = writeln!(svg, "<line class=\"axis\" x1=\"{l}\" y1=\"{t}\" x2=\"{l}\" y2=\"{b}\"/>", l = LEFT, t = TOP, b = bottom);
+    let _ = writeln!(svg, "<line class=\"axis\" x1=\"{l}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\"/>", l = LEFT, b = bottom, r = WIDTH - RIGHT);
+    for fraction in [0.0, 0.5, 1.0] {
+        let y = bottom - fraction * (bottom - TOP);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>", LEFT - 5.0, y + 4.0, tick(ymax * fraction));
+    }
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>", LEFT + (WIDTH - LEFT - RIGHT) / 2.0, HEIGHT - 8.0, escape(xlabel));
+    let _ = writeln!(
+        svg,
+        "<text x=\"15\" y=\"{y}\" text-anchor=\"middle\" transform=\"rotate(-90 15 {y})\">{}</text>",
+        escape(ylabel),
+        y = TOP + (bottom - TOP) / 2.0
+    );
+    svg
+}
+
+// bars of equal width, labels are (bar, text) on the x axis at the left edge of the bar
+fn bar_chart(title: &str, xlabel: &str, ylabel: &str, values: &[f64], labels: &[(usize, String)]) -> String {
+    let ymax = values.iter().cloned().fold(0.0, f64::max).max(1.0);
+    let height = HEIGHT - TOP - BOTTOM;
+    let width = (WIDTH - LEFT - RIGHT) / values.len().max(1) as f64;
+    let mut svg = frame(title, xlabel, ylabel, ymax);
+    for (i, &v) in values.iter().enumerate() {
+        let h = v / ymax * height;
+        let _ = writeln!(
+            svg,
+            "<rect class=\"bar\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"/>",
+            LEFT + i as f64 * width,
+            HEIGHT - BOTTOM - h,
+            (width * 0.9).max(0.5),
+            h
+        );
+    }
+    for (i, text) in labels {
+        let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>", LEFT + *i as f64 * width, HEIGHT - BOTTOM + 15.0, text);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// points are (x, y), the x axis goes from 0 to the largest x
+fn line_chart(title: &str, xlabel: &str, ylabel: &str, points: &[(f64, f64)]) -> String {
+    let xmax = points.iter().map(|p| p.0).fold(0.0, f64::max).max(1.0);
+    let ymax = points.iter().map(|p| p.1).fold(0.0, f64::max).max(1.0).ceil();
+    let width = WIDTH - LEFT - RIGHT;
+    let height = HEIGHT - TOP - BOTTOM;
+    let mut svg = frame(title, xlabel, ylabel, ymax);
+    let coordinates: Vec<String> = points
+        .iter()
+        .map(|(x, y)| format!("{:.1},{:.1}", LEFT + x / xmax * width, HEIGHT - BOTTOM - y / ymax * height))
+        .collect();
+    let _ = writeln!(svg, "<polyline class=\"line\" points=\"{}\"/>", coordinates.join(" "));
+    for fraction in [0.0, 0.5, 1.0] {
+        let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>", LEFT + fraction * width, HEIGHT - BOTTOM + 15.0, tick(xmax * fraction));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn tick(x: f64) -> String {
+    if x.fract() == 0.0 || x >= 100.0 {
+        format!("{:.0}", x)
+    } else {
+        format!("{:.1}", x)
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
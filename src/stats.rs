@@ -0,0 +1,601 @@
+// accumulator for the table output
+//
+// one is filled per file, or one per worker thread that are merged at the end (see pipeline.rs).
+// Read lengths are kept as counts per length, so memory does not grow with the number of reads
+use crate::error::{self, Error};
+use crate::modules;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::str::FromStr;
+
+// reads with a complexity below this count as low complexity in the table
+pub const LOW_COMPLEXITY: f64 = 0.5;
+
+// first line of a saved stats file, the number is the version of the format
+const STATS_FORMAT: &str = "faster_stats 1";
+
+// lengths below this can be counted in a vector indexed by length, longer ones are in a map
+const DENSE_LENGTHS: usize = 1 << 12;
+// the vector is only used once the map has this many distinct lengths below DENSE_LENGTHS, so the
+// many small accumulators (per channel, per time bin) stay small
+const DENSE_SWITCH: usize = 256;
+
+// exact number of reads per length, memory depends on the number of distinct lengths and not
+// on the number of reads. All length statistics are computed in one pass over it.
+#[derive(Clone, Default)]
+pub struct LengthCounts {
+    dense: Vec<i64>,
+    sparse: BTreeMap<i64, i64>,
+    // distinct lengths below DENSE_LENGTHS in the map, while there is no vector
+    short: usize,
+    // never use the vector, for accumulators with few reads
+    sparse_only: bool,
+    pub n: i64,
+    pub sum: i64,
+}
+
+impl LengthCounts {
+    pub fn sparse() -> Self {
+        LengthCounts {
+            sparse_only: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, len: i64) {
+        self.add_count(len, 1);
+    }
+
+    pub fn add_count(&mut self, len: i64, count: i64) {
+        let i = len as usize;
+        if i < DENSE_LENGTHS && !self.dense.is_empty() {
+            if i >= self.dense.len() {
+                self.dense.resize(i + 1, 0);
+            }
+            self.dense[i] += count;
+        } else {
+            let entry = self.sparse.entry(len).or_insert(0);
+            if *entry == 0 && i < DENSE_LENGTHS {
+                self.short += 1;
+            }
+            *entry += count;
+            if self.short > DENSE_SWITCH && !self.sparse_only {
+                self.make_dense();
+            }
+        }
+        self.n += count;
+        self.sum += len * count;
+    }
+
+    // move the lengths below DENSE_LENGTHS from the map to the vector, iter() relies on the vector
+    // holding only shorter lengths than the map
+    fn make_dense(&mut self) {
+        let long = self.sparse.split_off(&(DENSE_LENGTHS as i64));
+        let short = std::mem::replace(&mut self.sparse, long);
+        let max = short.keys().next_back().map_or(0, |&len| len as usize);
+        self.dense = vec![0; max + 1];
+        for (len, count) in short {
+            self.dense[len as usize] += count;
+        }
+        self.short = 0;
+    }
+
+    pub fn merge(&mut self, other: &LengthCounts) {
+        for (len, count) in other.iter() {
+            self.add_count(len, count);
+        }
+    }
+
+    // (length, count) in ascending length
+    pub fn iter(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.dense
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(len, &count)| (len as i64, count))
+            .chain(self.sparse.iter().map(|(&len, &count)| (len, count)))
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum as f64 / self.n as f64
+    }
+
+    // lengths at the given (0-based) ranks of the sorted lengths
+    pub fn at_ranks(&self, ranks: &[i64]) -> Vec<i64> {
+        let mut order: Vec<usize> = (0..ranks.len()).collect();
+        order.sort_by_key(|&i| ranks[i]);
+        let mut result = vec![0; ranks.len()];
+
+        let mut seen = 0;
+        let mut next = order.iter().peekable();
+        for (len, count) in self.iter() {
+            seen += count;
+            while let Some(&&i) = next.peek() {
+                if ranks[i] >= seen {
+                    break;
+                }
+                result[i] = len;
+                next.next();
+            }
+        }
+        result
+    }
+
+    // quartiles like they were always reported, rank n/4, n/2 and n/4 + n/2
+    pub fn quartiles(&self) -> [i64; 3] {
+        let q = self.at_ranks(&[self.n / 4, self.n / 2, self.n / 4 + self.n / 2]);
+        [q[0], q[1], q[2]]
+    }
+
+    // NX and LX for each fraction (0.5 for N50), LX is the number of reads needed to reach NX
+    //
+    // going from short to long reads, NX is the length of the read where the cumulative sum exceeds (1 - X) of all bases
+    pub fn nx(&self, fractions: &[f64]) -> Vec<(i64, i64)> {
+        let thresholds: Vec<i64> = fractions
+            .iter()
+            .map(|x| (self.sum as f64 * (1.0 - x)) as i64)
+            .collect();
+        let mut order: Vec<usize> = (0..fractions.len()).collect();
+        order.sort_by_key(|&i| thresholds[i]);
+        let mut result = vec![(0, 0); fractions.len()];
+
+        let mut cumsum = 0;
+        let mut reads_before = 0;
+        let mut next = order.iter().peekable();
+        for (len, count) in self.iter() {
+            while let Some(&&i) = next.peek() {
+                if cumsum + len * count <= thresholds[i] {
+                    break;
+                }
+                // first read in this bucket where the cumulative sum gets above the threshold
+                let k = if thresholds[i] >= cumsum { (thresholds[i] - cumsum) / len } else { 0 };
+                result[i] = (len, self.n - reads_before - k);
+                next.next();
+            }
+            cumsum += len * count;
+            reads_before += count;
+        }
+        result
+    }
+}
+
+#[derive(Clone)]
+pub struct TableStats {
+    pub reads: i64,
+    pub bases: i64,
+    pub num_n: i64,
+    pub qual20: i64,
+    pub qual30: i64,
+    pub minlen: i64,
+    pub maxlen: i64,
+    pub gc_bases: i64,
+    pub n_runs: i64,
+    pub fasta: bool,
+    pub lengths: LengthCounts,
+    pub phred_offset: u8,
+    // the complexity is only computed if one of its columns is requested
+    pub complexity: bool,
+    // reads with a complexity, fewer than reads if stats with and without it were merged
+    pub complexity_reads: i64,
+    pub complexity_sum: f64,
+    pub low_complexity: i64,
+    // histograms for the html report, only collected if a report is written
+    pub report: bool,
+    // reads per 'mean' read phred score, rounded down
+    pub qual_hist: Vec<i64>,
+    // sum of the error probabilities per position in the read
+    pub pos_error: Vec<f64>,
+    // for the NG<x>, LG<x> and depth columns, not saved with the stats
+    pub genome_size: Option<i64>,
+}
+
+impl Default for TableStats {
+    fn default() -> Self {
+        TableStats {
+            reads: 0,
+            bases: 0,
+            num_n: 0,
+            qual20: 0,
+            qual30: 0,
+            minlen: i64::MAX,
+            maxlen: 0,
+            gc_bases: 0,
+            n_runs: 0,
+            fasta: false,
+            lengths: LengthCounts::default(),
+            phred_offset: 33,
+            complexity: false,
+            complexity_reads: 0,
+            complexity_sum: 0.0,
+            low_complexity: 0,
+            report: false,
+            qual_hist: Vec::new(),
+            pos_error: Vec::new(),
+            genome_size: None,
+        }
+    }
+}
+
+impl TableStats {
+    pub fn new(phred_offset: u8) -> Self {
+        TableStats {
+            phred_offset,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, seq: &[u8], qual: &[u8], fasta: bool) {
+        let len = seq.len() as i64; // here have to accomodate bigger numbers, as bases can get > 2^32
+
+        self.reads += 1;
+        self.bases += len;
+        let (num_n, gc_bases, n_runs) = modules::get_base_counts(seq);
+        self.num_n += num_n;
+        self.qual20 += modules::get_qual_bases(qual, self.phred_offset + 20);
+        self.qual30 += modules::get_qual_bases(qual, self.phred_offset + 30);
+        self.gc_bases += gc_bases;
+        self.n_runs += n_runs;
+        self.fasta |= fasta;
+        self.minlen = len.min(self.minlen);
+        self.maxlen = len.max(self.maxlen);
+        self.lengths.add(len);
+        if self.complexity {
+            let complexity = modules::get_complexity(seq);
+            self.complexity_reads += 1;
+            self.complexity_sum += complexity;
+            if complexity < LOW_COMPLEXITY {
+                self.low_complexity += 1;
+            }
+        }
+        if self.report && !fasta && !qual.is_empty() {
+            if qual.len() > self.pos_error.len() {
+                self.pos_error.resize(qual.len(), 0.0);
+            }
+            let mut error_sum = 0.0;
+            for (i, &q) in qual.iter().enumerate() {
+                let p = 10f64.powf(-(q as f64 - self.phred_offset as f64) / 10.0);
+                self.pos_error[i] += p;
+                error_sum += p;
+            }
+            let meanq = (-10.0 * (error_sum / qual.len() as f64).log10()).max(0.0) as usize;
+            if meanq >= self.qual_hist.len() {
+                self.qual_hist.resize(meanq + 1, 0);
+            }
+            self.qual_hist[meanq] += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &TableStats) {
+        self.reads += other.reads;
+        self.bases += other.bases;
+        self.num_n += other.num_n;
+        self.qual20 += other.qual20;
+        self.qual30 += other.qual30;
+        self.gc_bases += other.gc_bases;
+        self.n_runs += other.n_runs;
+        self.fasta |= other.fasta;
+        self.minlen = self.minlen.min(other.minlen);
+        self.maxlen = self.maxlen.max(other.maxlen);
+        self.lengths.merge(&other.lengths);
+        self.complexity |= other.complexity;
+        self.complexity_reads += other.complexity_reads;
+        self.complexity_sum += other.complexity_sum;
+        self.low_complexity += other.low_complexity;
+        self.report |= other.report;
+        add_vec(&mut self.qual_hist, &other.qual_hist);
+        add_vec(&mut self.pos_error, &other.pos_error);
+        self.genome_size = self.genome_size.or(other.genome_size);
+    }
+
+    // one line of the table with the selected columns, without newline
+    pub fn row(&self, infile: &str, columns: &[String]) -> String {
+        // everything that needs a pass over the lengths is collected first
+        let percentiles: Vec<i64> = columns
+            .iter()
+            .filter_map(|c| column_number(c, "P"))
+            .map(|p| ((self.lengths.n as f64 * p / 100.0) as i64).min(self.lengths.n - 1))
+            .collect();
+        let fractions: Vec<f64> = columns
+            .iter()
+            .filter_map(|c| column_number(c, "N").or_else(|| column_number(c, "L")))
+            .map(|x| x / 100.0)
+            .collect();
+        // NG<x> is NX with x of the genome size instead of x of the bases, NA above the bases
+        let genome = self.genome_size.unwrap_or(0) as f64;
+        let ng_fractions: Vec<f64> = columns
+            .iter()
+            .filter_map(|c| column_number(c, "NG").or_else(|| column_number(c, "LG")))
+            .map(|x| x / 100.0 * genome / self.lengths.sum as f64)
+            .collect();
+        let mut percentiles = self.lengths.at_ranks(&percentiles).into_iter();
+        let mut nx = self.lengths.nx(&fractions).into_iter();
+        let mut ngx = ng_fractions.iter().zip(self.lengths.nx(&ng_fractions));
+        let depth = |min_len: i64| match self.genome_size {
+            Some(size) => {
+                let bases: i64 = self.lengths.iter().filter(|&(len, _)| len >= min_len).map(|(len, count)| len * count).sum();
+                format!("{:.2}", bases as f64 / size as f64)
+            }
+            None => "NA".to_string(),
+        };
+        let [quart1, quart2, quart3] = self.lengths.quartiles();
+
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|c| match c.as_str() {
+                "file" => infile.to_string(),
+                "reads" => self.reads.to_string(),
+                "bases" => self.bases.to_string(),
+                "n_bases" => self.num_n.to_string(),
+                "min_len" => self.minlen.to_string(),
+                "max_len" => self.maxlen.to_string(),
+                "mean_len" => format!("{:.2}", self.lengths.mean()),
+                "Q1" => quart1.to_string(),
+                "Q2" => quart2.to_string(),
+                "Q3" => quart3.to_string(),
+                "Q20_percent" => self.qual_percent(self.qual20),
+                "Q30_percent" => self.qual_percent(self.qual30),
+                "GC_percent" => format!("{:.2}", self.gc_bases as f64 / self.bases as f64 * 100.0),
+                "N_runs" => self.n_runs.to_string(),
+                // e.g. merged from saved stats of which some were computed without these columns
+                "complexity" | "low_complexity_percent" if !self.complexity || self.complexity_reads != self.reads => "NA".to_string(),
+                "complexity" => format!("{:.4}", self.complexity_sum / self.reads as f64),
+                "low_complexity_percent" => format!("{:.2}", self.low_complexity as f64 / self.reads as f64 * 100.0),
+                "depth" => depth(0),
+                _ if c.starts_with("depth_") => depth(modules::parse_size(&c["depth_".len()..]).unwrap()),
+                _ if c.starts_with("NG") || c.starts_with("LG") => match ngx.next().unwrap() {
+                    (&x, _) if !(x > 0.0 && x <= 1.0) => "NA".to_string(),
+                    (_, (ng, _)) if c.starts_with('N') => ng.to_string(),
+                    (_, (_, lg)) => lg.to_string(),
+                },
+                _ if c.starts_with('P') => percentiles.next().unwrap().to_string(),
+                _ if c.starts_with('N') => nx.next().unwrap().0.to_string(),
+                _ => nx.next().unwrap().1.to_string(),
+            })
+            .collect();
+        fields.join("\t")
+    }
+
+    // the accumulator as gzipped text, one line per field. Partial stats (e.g. of chunks on different
+    // machines) can be loaded and merged later, the table row is then exactly the same as for all
+    // reads at once. Floats are written with all digits, so they are read back unchanged
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let mut out = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+        writeln!(out, "{}", STATS_FORMAT)?;
+        let counts = [
+            ("reads", self.reads),
+            ("bases", self.bases),
+            ("n_bases", self.num_n),
+            ("qual20", self.qual20),
+            ("qual30", self.qual30),
+            ("min_len", self.minlen),
+            ("max_len", self.maxlen),
+            ("gc_bases", self.gc_bases),
+            ("n_runs", self.n_runs),
+            ("low_complexity", self.low_complexity),
+            ("complexity_reads", self.complexity_reads),
+        ];
+        for (key, value) in counts {
+            writeln!(out, "{} {}", key, value)?;
+        }
+        writeln!(out, "phred_offset {}", self.phred_offset)?;
+        writeln!(out, "fasta {}", self.fasta)?;
+        writeln!(out, "complexity {}", self.complexity)?;
+        writeln!(out, "complexity_sum {}", self.complexity_sum)?;
+        writeln!(out, "report {}", self.report)?;
+        writeln!(out, "qual_hist {}", join(&self.qual_hist))?;
+        writeln!(out, "pos_error {}", join(&self.pos_error))?;
+        let lengths: Vec<String> = self.lengths.iter().map(|(len, count)| format!("{}:{}", len, count)).collect();
+        writeln!(out, "lengths {}", lengths.join(" "))?;
+        out.finish()?.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<TableStats, Error> {
+        let invalid = |what: &str| Error::InvalidInput(format!("not a faster stats file ({})", what));
+        // broken gzip data is invalid input too, not a read error
+        let read_error = |e: io::Error| match error::input_error(e) {
+            Error::InvalidInput(message) => invalid(&message),
+            e => e,
+        };
+        let reader = BufReader::new(MultiGzDecoder::new(File::open(path).map_err(Error::Input)?));
+        let mut lines = reader.lines();
+        match lines.next().transpose().map_err(read_error)? {
+            Some(line) if line == STATS_FORMAT => {}
+            _ => return Err(invalid("unknown format")),
+        }
+
+        let mut stats = TableStats::default();
+        // not in files saved before it was added, there all reads had a complexity or none
+        let mut complexity_reads = None;
+        for line in lines {
+            let line = line.map_err(read_error)?;
+            let (key, value) = line.split_once(' ').ok_or_else(|| invalid(&line))?;
+            let number = || value.parse::<i64>().map_err(|_| invalid(key));
+            match key {
+                "reads" => stats.reads = number()?,
+                "bases" => stats.bases = number()?,
+                "n_bases" => stats.num_n = number()?,
+                "qual20" => stats.qual20 = number()?,
+                "qual30" => stats.qual30 = number()?,
+                "min_len" => stats.minlen = number()?,
+                "max_len" => stats.maxlen = number()?,
+                "gc_bases" => stats.gc_bases = number()?,
+                "n_runs" => stats.n_runs = number()?,
+                "low_complexity" => stats.low_complexity = number()?,
+                "complexity_reads" => complexity_reads = Some(number()?),
+                "phred_offset" => stats.phred_offset = value.parse().map_err(|_| invalid(key))?,
+                "fasta" => stats.fasta = value == "true",
+                "complexity" => stats.complexity = value == "true",
+                "complexity_sum" => stats.complexity_sum = value.parse().map_err(|_| invalid(key))?,
+                "report" => stats.report = value == "true",
+                "qual_hist" => stats.qual_hist = split(value).ok_or_else(|| invalid(key))?,
+                "pos_error" => stats.pos_error = split(value).ok_or_else(|| invalid(key))?,
+                "lengths" => {
+                    for pair in value.split_whitespace() {
+                        let (len, count) = pair.split_once(':').ok_or_else(|| invalid(key))?;
+                        let len = len.parse::<i64>().ok().filter(|&len| len >= 0).ok_or_else(|| invalid(key))?;
+                        stats.lengths.add_count(len, count.parse().map_err(|_| invalid(key))?);
+                    }
+                }
+                _ => return Err(invalid(key)),
+            }
+        }
+        stats.complexity_reads = complexity_reads.unwrap_or(if stats.complexity { stats.reads } else { 0 });
+        Ok(stats)
+    }
+
+    // quality columns are meaningless for fasta (e.g. assemblies), report NA
+    fn qual_percent(&self, qual: i64) -> String {
+        if self.fasta {
+            "NA".to_string()
+        } else {
+            format!("{:.2}", qual as f64 / self.bases as f64 * 100.0)
+        }
+    }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn split<T: FromStr>(values: &str) -> Option<Vec<T>> {
+    values.split_whitespace().map(|x| x.parse().ok()).collect()
+}
+
+// element-wise sum, a gets longer if b is longer
+fn add_vec<T: Copy + Default + std::ops::AddAssign>(a: &mut Vec<T>, b: &[T]) {
+    if b.len() > a.len() {
+        a.resize(b.len(), T::default());
+    }
+    for (x, &y) in a.iter_mut().zip(b) {
+        *x += y;
+    }
+}
+
+pub const DEFAULT_COLUMNS: [&str; 16] = [
+    "file", "reads", "bases", "n_bases", "min_len", "max_len", "mean_len", "Q1", "Q2", "Q3", "N50",
+    "Q20_percent", "Q30_percent", "L50", "GC_percent", "N_runs",
+];
+
+const NAMED_COLUMNS: [&str; 16] = [
+    "file", "reads", "bases", "n_bases", "min_len", "max_len", "mean_len", "Q1", "Q2", "Q3",
+    "Q20_percent", "Q30_percent", "GC_percent", "N_runs", "complexity", "low_complexity_percent",
+];
+
+// the number in columns like P95 (length percentile), N90, L90, NG50 or LG50, None for other columns
+fn column_number(column: &str, prefix: &str) -> Option<f64> {
+    if NAMED_COLUMNS.contains(&column) || !column.starts_with(prefix) {
+        return None;
+    }
+    let x = column[prefix.len()..].parse::<f64>().ok()?;
+    match prefix {
+        "P" if (0.0..=100.0).contains(&x) => Some(x),
+        "N" | "L" | "NG" | "LG" if x > 0.0 && x <= 100.0 => Some(x),
+        _ => None,
+    }
+}
+
+// columns that need the genome size: NG<x>, LG<x>, depth and depth_<length> (bases in reads of at
+// least length, e.g. depth_10k)
+pub fn is_genome_column(column: &str) -> bool {
+    column == "depth"
+        || column.strip_prefix("depth_").and_then(modules::parse_size).is_some()
+        || column_number(column, "NG").is_some()
+        || column_number(column, "LG").is_some()
+}
+
+pub fn check_columns(columns: &[String]) -> Result<(), String> {
+    for c in columns {
+        let known = NAMED_COLUMNS.contains(&c.as_str())
+            || column_number(c, "P").is_some()
+            || column_number(c, "N").is_some()
+            || column_number(c, "L").is_some()
+            || is_genome_column(c);
+        if !known {
+            return Err(format!(
+                "Unknown table column '{}', use one of {} or P<x> (length percentile), N<x> and L<x> with x between 0 and 100, NG<x>, LG<x>, depth and depth_<length> with --genome_size",
+                c,
+                NAMED_COLUMNS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NX and LX like they were computed before, by sorting all lengths
+    fn sorted_nx(lengths: &[i64], x: f64) -> (i64, i64) {
+        let mut sorted = lengths.to_vec();
+        sorted.sort_unstable();
+        let threshold = (sorted.iter().sum::<i64>() as f64 * (1.0 - x)) as i64;
+        let mut cumsum = 0;
+        for (i, len) in sorted.iter().enumerate() {
+            cumsum += len;
+            if cumsum > threshold {
+                return (*len, (sorted.len() - i) as i64);
+            }
+        }
+        (0, 0)
+    }
+
+    fn counts(lengths: &[i64]) -> LengthCounts {
+        let mut counts = LengthCounts::default();
+        for &len in lengths {
+            counts.add(len);
+        }
+        counts
+    }
+
+    fn check(lengths: &[i64]) {
+        let counts = counts(lengths);
+        let fractions = [0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 1.0];
+        let expected: Vec<(i64, i64)> = fractions.iter().map(|&x| sorted_nx(lengths, x)).collect();
+        assert_eq!(counts.nx(&fractions), expected, "nx of {:?}", lengths);
+
+        let mut sorted = lengths.to_vec();
+        sorted.sort_unstable();
+        let ranks: Vec<i64> = (0..sorted.len() as i64).rev().collect();
+        let expected: Vec<i64> = ranks.iter().map(|&r| sorted[r as usize]).collect();
+        assert_eq!(counts.at_ranks(&ranks), expected, "ranks of {:?}", lengths);
+    }
+
+    #[test]
+    fn empty() {
+        let counts = LengthCounts::default();
+        assert_eq!(counts.nx(&[0.5, 1.0]), vec![(0, 0), (0, 0)]);
+        assert_eq!(counts.at_ranks(&[0]), vec![0]);
+    }
+
+    #[test]
+    fn zero_length_reads() {
+        check(&[0, 0, 0]);
+        check(&[0, 0, 5, 0, 10]);
+    }
+
+    #[test]
+    fn ties_at_the_threshold() {
+        // the cumulative sum reaches exactly half of the bases after the first 100
+        check(&[100, 100, 100, 100]);
+        check(&[50, 50, 100]);
+        check(&[10, 10, 10, 30]);
+        check(&[1, 1, 2, 2, 2, 4]);
+    }
+
+    #[test]
+    fn dense_and_sparse_lengths() {
+        // more distinct short lengths than DENSE_SWITCH, and lengths above DENSE_LENGTHS
+        let lengths: Vec<i64> = (0..3000).map(|i| (i * 7919) % 5000 + (i % 3) * 20000).collect();
+        check(&lengths);
+
+        let mut merged = counts(&lengths[..10]);
+        merged.merge(&counts(&lengths[10..]));
+        assert_eq!(merged.iter().collect::<Vec<_>>(), counts(&lengths).iter().collect::<Vec<_>>());
+    }
+}
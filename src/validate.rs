@@ -0,0 +1,242 @@
+// validation of fastq (and fasta) files with the record number and byte offset of every problem
+//
+// the file is read line by line and not with kseq, which stops at the first problem without a position.
+// Byte offsets are in the uncompressed data.
+use crate::error::{self, Error};
+use crate::modules::id_hash;
+use crate::pipeline;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+
+pub const EXIT_CLEAN: i32 = 0;
+pub const EXIT_WARNINGS: i32 = 1;
+// same as invalid input data in the other modes
+pub const EXIT_ERRORS: i32 = error::EXIT_INVALID_INPUT;
+
+// exit code for the problems found in one file, the worst over all files is used
+pub fn exit_code(warnings: u64, errors: u64) -> i32 {
+    if errors > 0 {
+        EXIT_ERRORS
+    } else if warnings > 0 {
+        EXIT_WARNINGS
+    } else {
+        EXIT_CLEAN
+    }
+}
+
+struct Validator<'a> {
+    infile: &'a str,
+    out: &'a mut dyn Write,
+    phred_offset: u8,
+    record: u64,
+    warnings: u64,
+    errors: u64,
+    // 128 bit hashes of the ids and the record where they were first seen, full ids would take too much memory
+    ids: HashMap<u128, u64>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, offset: u64, error: bool, message: &str) -> Result<(), Error> {
+        if error {
+            self.errors += 1;
+        } else {
+            self.warnings += 1;
+        }
+        let level = if error { "error" } else { "warning" };
+        writeln!(self.out, "{}\t{}\t{}\t{}\t{}", self.infile, self.record, offset, level, message)?;
+        Ok(())
+    }
+
+    fn check_id(&mut self, header: &[u8], offset: u64) -> Result<(), Error> {
+        let id_end = header
+            .iter()
+            .position(|c| c.is_ascii_whitespace())
+            .unwrap_or(header.len());
+        let id = &header[1..id_end];
+        if id.is_empty() {
+            return self.report(offset, true, "empty read id");
+        }
+        if header.iter().any(|&c| c != b'\t' && !(b' '..=b'~').contains(&c)) {
+            self.report(offset, false, "non printable character in header")?;
+        }
+        let hash = id_hash(id);
+        if let Some(first) = self.ids.get(&hash).copied() {
+            let message = format!("duplicate read id {}, first seen in record {}", String::from_utf8_lossy(id), first);
+            return self.report(offset, false, &message);
+        }
+        self.ids.insert(hash, self.record);
+        Ok(())
+    }
+
+    fn check_seq(&mut self, seq: &[u8], offset: u64) -> Result<(), Error> {
+        if seq.is_empty() {
+            return self.report(offset, true, "empty sequence");
+        }
+        for (i, &base) in seq.iter().enumerate() {
+            match base.to_ascii_uppercase() {
+                b'A' | b'C' | b'G' | b'T' | b'N' => {}
+                // IUPAC ambiguity codes, U and gaps are valid but unusual for reads
+                b'R' | b'Y' | b'K' | b'M' | b'S' | b'W' | b'B' | b'D' | b'H' | b'V' | b'U' | b'-' | b'.' => {
+                    let message = format!("unusual base '{}' at position {}", base as char, i + 1);
+                    return self.report(offset + i as u64, false, &message);
+                }
+                _ => {
+                    let message = format!("invalid base '{}' at position {}", String::from_utf8_lossy(&[base]), i + 1);
+                    return self.report(offset + i as u64, true, &message);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_qual(&mut self, qual: &[u8], seqlen: usize, offset: u64) -> Result<(), Error> {
+        if qual.len() != seqlen {
+            let message = format!("quality length {} is different from sequence length {}", qual.len(), seqlen);
+            return self.report(offset, true, &message);
+        }
+        // Solexa+64 goes down to -5 (';'), everything below is an error
+        let lowest = if self.phred_offset == 64 { b';' } else { b'!' };
+        for (i, &q) in qual.iter().enumerate() {
+            if q < lowest || q > b'~' {
+                let message = format!("quality '{}' at position {} is out of range", String::from_utf8_lossy(&[q]), i + 1);
+                return self.report(offset + i as u64, true, &message);
+            }
+            if q < self.phred_offset {
+                let message = format!("negative (Solexa) quality '{}' at position {}", q as char, i + 1);
+                return self.report(offset + i as u64, false, &message);
+            }
+        }
+        Ok(())
+    }
+}
+
+// one line without the line end, None at the end of the file
+fn read_line<R: BufRead>(reader: &mut R, line: &mut Vec<u8>, offset: &mut u64) -> Result<Option<(u64, bool)>, Error> {
+    line.clear();
+    let n = reader.read_until(b'\n', line).map_err(error::input_error)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    let start = *offset;
+    *offset += n as u64;
+    let complete = line.last() == Some(&b'\n');
+    if complete {
+        line.pop();
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(Some((start, complete)))
+}
+
+// validate one file, the problems are written to out as a table. Returns records, warnings and errors
+pub fn validate(infile: &str, phred_offset: u8, out: &mut dyn Write) -> Result<(u64, u64, u64), Error> {
+    let reader = pipeline::spawn_reader(infile, 1).map_err(Error::Input)?;
+    let mut reader = BufReader::with_capacity(1 << 16, reader);
+    let mut v = Validator {
+        infile,
+        out,
+        phred_offset,
+        record: 0,
+        warnings: 0,
+        errors: 0,
+        ids: HashMap::new(),
+    };
+
+    let mut offset = 0;
+    let mut last_complete = true;
+    match check_records(&mut v, &mut reader, &mut offset, &mut last_complete) {
+        Ok(()) => {}
+        // e.g. a truncated .gz file, the records up to here were checked
+        Err(Error::InvalidInput(message)) => {
+            v.report(offset, true, &format!("truncated or corrupt compressed input ({})", message))?;
+            last_complete = true;
+        }
+        Err(e) => return Err(e),
+    }
+    if !last_complete {
+        v.report(offset, false, "no newline at the end of the file")?;
+    }
+    Ok((v.record, v.warnings, v.errors))
+}
+
+// the records of the file, offset is where reading stopped. Errors of the decompression are
+// returned as invalid input
+fn check_records<R: BufRead>(v: &mut Validator, reader: &mut R, offset: &mut u64, last_complete: &mut bool) -> Result<(), Error> {
+    let mut header = Vec::new();
+    let mut seq = Vec::new();
+    let mut line = Vec::new();
+    let mut next = read_line(reader, &mut header, offset)?;
+
+    while let Some((start, complete)) = next {
+        *last_complete = complete;
+        v.record += 1;
+        match header.first() {
+            Some(b'@') => {
+                v.check_id(&header, start)?;
+                let (seq_start, complete) = match read_line(reader, &mut seq, offset)? {
+                    Some(line_start) => line_start,
+                    None => {
+                        v.report(*offset, true, "truncated record, no sequence")?;
+                        break;
+                    }
+                };
+                *last_complete = complete;
+                v.check_seq(&seq, seq_start)?;
+                let (sep_start, complete) = match read_line(reader, &mut line, offset)? {
+                    Some(line_start) => line_start,
+                    None => {
+                        v.report(*offset, true, "truncated record, no '+' line")?;
+                        break;
+                    }
+                };
+                *last_complete = complete;
+                if line.first() != Some(&b'+') {
+                    v.report(sep_start, true, "the line after the sequence does not start with '+'")?;
+                } else if line.len() > 1 && line[1..] != header[1..] {
+                    v.report(sep_start, false, "the '+' line does not repeat the header")?;
+                }
+                let (qual_start, complete) = match read_line(reader, &mut line, offset)? {
+                    Some(line_start) => line_start,
+                    None => {
+                        v.report(*offset, true, "truncated record, no quality")?;
+                        break;
+                    }
+                };
+                *last_complete = complete;
+                v.check_qual(&line, seq.len(), qual_start)?;
+                next = read_line(reader, &mut header, offset)?;
+            }
+            Some(b'>') => {
+                v.check_id(&header, start)?;
+                // sequence lines up to the next header
+                seq.clear();
+                let seq_start = *offset;
+                loop {
+                    next = read_line(reader, &mut line, offset)?;
+                    match next {
+                        Some((_, complete)) if line.first() != Some(&b'>') => {
+                            *last_complete = complete;
+                            seq.extend_from_slice(&line);
+                        }
+                        _ => break,
+                    }
+                }
+                v.check_seq(&seq, seq_start)?;
+                std::mem::swap(&mut header, &mut line);
+            }
+            _ => {
+                v.report(start, true, "expected a header line starting with '@' or '>'")?;
+                // skip to the next line that looks like a header
+                loop {
+                    next = read_line(reader, &mut header, offset)?;
+                    match next {
+                        Some(_) if !matches!(header.first(), Some(b'@') | Some(b'>')) => continue,
+                        _ => break,
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+// watch directories for new fastq files, e.g. fastq_pass/ while MinKNOW is writing to it
+//
+// the directories (and their subdirectories) are scanned every interval. A new file is read once
+// its size did not change between two scans, so files that are still being written are not read
+// too early. Every file is read only once and its stats are merged into the cumulative stats,
+// which are printed as a new table row (or written to a stats file) after every scan with new files
+use crate::error::Error;
+use crate::stats::TableStats;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const EXTENSIONS: [&str; 6] = ["fastq", "fq", "fasta", "fa", "fna", "fas"];
+
+pub struct Watch<'a> {
+    pub dirs: Vec<&'a str>,
+    pub interval: Duration,
+    // stop when no new files came for this long, None to watch until interrupted
+    pub idle: Option<Duration>,
+    // rewrite this file instead of printing a row to stdout
+    pub stats_file: Option<&'a str>,
+    pub columns: Vec<String>,
+    pub header: bool,
+}
+
+impl Watch<'_> {
+    // task reads one file and returns its stats, keep_going skips files that can not be read
+    pub fn run<F>(&self, keep_going: bool, mut task: F) -> Result<(), Error>
+    where
+        F: FnMut(&str) -> Result<Option<TableStats>, Error>,
+    {
+        let name = self.dirs.join(",");
+        let mut total = TableStats::default();
+        let mut done: HashSet<PathBuf> = HashSet::new();
+        let mut sizes: HashMap<PathBuf, u64> = HashMap::new();
+        let mut last_new = Instant::now();
+        if self.header && self.stats_file.is_none() {
+            println!("{}", self.columns.join("\t"));
+        }
+
+        loop {
+            let mut files = Vec::new();
+            for dir in &self.dirs {
+                find_files(Path::new(dir), &mut files).map_err(Error::Input)?;
+            }
+            files.sort();
+            let mut updated = false;
+            let mut pending = false;
+            for (path, size) in files {
+                if done.contains(&path) {
+                    continue;
+                }
+                // still growing (or seen for the first time)
+                if sizes.insert(path.clone(), size) != Some(size) {
+                    pending = true;
+                    continue;
+                }
+                let file = path.to_string_lossy();
+                match task(&file) {
+                    Ok(Some(stats)) => total.merge(&stats),
+                    Ok(None) => {}
+                    Err(e) if keep_going && !e.is_fatal() => eprintln!("{}: {}", file, e),
+                    Err(e) => return Err(e),
+                }
+                done.insert(path);
+                updated = true;
+            }
+
+            if updated {
+                last_new = Instant::now();
+                let row = total.row(&name, &self.columns);
+                match self.stats_file {
+                    Some(path) => self.write_stats(path, &row)?,
+                    None => println!("{}", row),
+                }
+            } else if pending {
+                last_new = Instant::now();
+            }
+            if self.idle.is_some_and(|idle| last_new.elapsed() >= idle) {
+                return Ok(());
+            }
+            thread::sleep(self.interval);
+        }
+    }
+
+    // the file is replaced at once, so it is never read half written
+    fn write_stats(&self, path: &str, row: &str) -> Result<(), Error> {
+        let tmp = format!("{}.tmp", path);
+        let mut out = fs::File::create(&tmp)?;
+        if self.header {
+            writeln!(out, "{}", self.columns.join("\t"))?;
+        }
+        writeln!(out, "{}", row)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+// fastx files (also gzipped) with their size, recursively
+fn find_files(dir: &Path, files: &mut Vec<(PathBuf, u64)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            find_files(&path, files)?;
+        } else if is_fastx(&path) {
+            files.push((path, metadata.len()));
+        }
+    }
+    Ok(())
+}
+
+fn is_fastx(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let name = name.trim_end_matches(".gz").trim_end_matches(".bgz");
+    match name.rsplit_once('.') {
+        Some((_, ext)) => EXTENSIONS.contains(&ext),
+        None => false,
+    }
+}
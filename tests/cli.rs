@@ -29,3 +29,582 @@ fn find_content_in_len() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn fasta_table_has_na_quality() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-ts").arg("tests/test.fasta");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "600\tNA\tNA\t1\t47.60\t3") );
+
+    Ok(())
+}
+
+#[test]
+fn convert_fasta_to_fastq() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--to_fastq").arg("I").arg("tests/test.fasta");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "@contig_3").and(predicate::str::contains("+\nIIIIIIIIII")) );
+
+    Ok(())
+}
+
+#[test]
+fn trim_fasta_stays_fasta() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--trim_front").arg("5").arg("tests/test.fasta");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( ">contig_1  len=600\nAGACAATTACATAACATACACG" ) )
+        .stdout(predicate::str::contains( "\n+\n" ).not() );
+
+    Ok(())
+}
+
+#[test]
+fn threads_keep_input_order() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--threads").arg("2").arg("tests/test.fasta").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match( "^file\t[^\n]+\ntests/test.fasta\t[^\n]+\ntests/test.fastq\t" )? );
+
+    Ok(())
+}
+
+#[test]
+fn threads_on_bgzf_file() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-ts").arg("--threads").arg("3").arg("tests/test.fastq.bgz");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "10\t18931\t0\t165\t8490\t1893.10\t249\t453\t2440\t5263\t51.45\t16.33") );
+
+    Ok(())
+}
+
+#[test]
+fn table_selected_columns() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--columns").arg("file,reads,N10,N90").arg("--percentiles").arg("5,95").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "file\treads\tN10\tN90\tP5\tP95\ntests/test.fastq\t10\t8490\t453\t165\t8490\n") );
+
+    Ok(())
+}
+
+#[test]
+fn table_unknown_column() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--columns").arg("file,foo").arg("tests/test.fastq");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains( "Unknown table column 'foo'") );
+
+    Ok(())
+}
+
+#[test]
+fn table_total_row() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-ts").arg("--total").arg("tests/test.fastq").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "total\t20\t37862\t0\t165\t8490\t1893.10\t249\t453\t2440\t5263\t51.45\t16.33\t3\t") );
+
+    Ok(())
+}
+
+#[test]
+fn per_read_table() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--per_read").arg("--fields").arg("id,length,n_count,first5").arg("tests/test.fasta");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "id\tlength\tn_count\tfirst5\ncontig_1\t600\t20\tGCTAA\n") );
+
+    Ok(())
+}
+
+#[test]
+fn phred64_auto_detection() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-ts").arg("--phred").arg("auto").arg("tests/test64.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "3\t3110\t0\t249\t2440\t1036.67\t249\t421\t421\t2440\t53.54\t16.33") );
+
+    Ok(())
+}
+
+#[test]
+fn convert_phred64_to_phred33() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--to_phred33").arg("--phred").arg("64").arg("tests/test64.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "+\n*%#$'#\"#%&+./,#%%%%##$$$#$%'(224439::6789<??KD-;4:;;;72/-" ) );
+
+    Ok(())
+}
+
+#[test]
+fn validate_reports_problems() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--validate").arg("tests/invalid.fastq");
+
+    cmd.assert()
+        .code(3)
+        .stdout(predicate::str::contains( "tests/invalid.fastq\t1\t8\terror\tinvalid base 'X' at position 5\n" )
+            .and(predicate::str::contains( "tests/invalid.fastq\t2\t17\twarning\tduplicate read id r1" ))
+            .and(predicate::str::contains( "tests/invalid.fastq\t3\t48\terror\tquality length 2" )) );
+
+    Ok(())
+}
+
+#[test]
+fn validate_clean_file() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--validate").arg("-s").arg("tests/test.fastq");
+
+    cmd.assert()
+        .code(0)
+        .stdout(predicate::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn validate_truncated_gzip() -> Result<(), Box<dyn std::error::Error>> {
+
+    // like head -c 5000 tests/test.fastq.bgz
+    let gz = std::fs::read("tests/test.fastq.bgz")?;
+    let truncated = std::env::temp_dir().join("faster_truncated.fastq.gz");
+    std::fs::write(&truncated, &gz[..5000])?;
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--validate").arg(&truncated);
+
+    cmd.assert()
+        .code(3)
+        .stdout(predicate::str::contains( "\terror\ttruncated or corrupt compressed input" ) );
+
+    Ok(())
+}
+
+#[test]
+fn missing_file_exit_code() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--table").arg("tests/does_not_exist.fastq");
+
+    cmd.assert()
+        .code(4)
+        .stderr(predicate::str::contains( "tests/does_not_exist.fastq" ) );
+
+    Ok(())
+}
+
+#[test]
+fn not_fastx_exit_code() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("README.md");
+
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains( "README.md: invalid input" ) );
+
+    Ok(())
+}
+
+#[test]
+fn invalid_argument_exit_code() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--nx").arg("200").arg("tests/test.fastq");
+
+    cmd.assert()
+        .code(2);
+
+    Ok(())
+}
+
+#[test]
+fn keep_going_after_missing_file() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--table").arg("-k").arg("tests/does_not_exist.fastq").arg("tests/test.fastq");
+
+    cmd.assert()
+        .code(4)
+        .stdout(predicate::str::contains( "tests/test.fastq\t" ) );
+
+    Ok(())
+}
+
+#[test]
+fn filter_by_n_count() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--filtern").arg("10").arg("tests/test.fasta");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( ">contig_1" ).not())
+        .stderr(predicate::str::contains( "tests/test.fasta: 1 reads discarded" ) );
+
+    Ok(())
+}
+
+#[test]
+fn table_complexity_columns() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--columns").arg("file,complexity,low_complexity_percent").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "tests/test.fastq\t0.9504\t0.00" ) );
+
+    Ok(())
+}
+
+#[test]
+fn filter_by_gc_range() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--filtergc").arg("0.0,0.3").arg("tests/test.fasta");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains( "tests/test.fasta: 3 reads discarded" ) );
+
+    Ok(())
+}
+
+#[test]
+fn table_grouped_by_channel() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--group_by").arg("channel").arg("--columns").arg("file,reads,bases").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "channel\tfile\treads\tbases\n7\ttests/test.fastq\t1\t2440\n46\ttests/test.fastq\t2\t874\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn channels_of_the_whole_flowcell() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--channels").arg("--channel_count").arg("512").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "tests/test.fastq\t126\t1\t819\n" ) )
+        .stdout(predicate::str::contains( "tests/test.fastq\t512\t0\t0\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn channels_with_huge_channel_number() -> Result<(), Box<dyn std::error::Error>> {
+
+    let reads = std::fs::read_to_string("tests/test.fastq")?.replace("ch=55 ", "ch=18446744073709551615 ");
+    let file = std::env::temp_dir().join("faster_huge_channel.fastq");
+    std::fs::write(&file, reads)?;
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--channels").arg(&file);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "\t3000\t0\t0\n" ) )
+        .stdout(predicate::str::contains( "\t18446744073709551615\t2\t" ) );
+
+    Ok(())
+}
+
+#[test]
+fn yield_over_time() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--yield_time").arg("1").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "tests/test.fastq\t0.02\t6\t10558\t11.36\t8490\t10\t18931\t11.14\t5263" ) );
+
+    Ok(())
+}
+
+#[test]
+fn yield_from_given_run_start() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--yield_time").arg("60").arg("--run_start").arg("2019-10-30T08:30:00Z").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "tests/test.fastq\t0.00\t0\t0\tNA\t0\t0\t0\tNA\t0\n" ) )
+        .stdout(predicate::str::contains( "tests/test.fastq\t1.00\t10\t18931\t" ) );
+
+    Ok(())
+}
+
+#[test]
+fn select_by_channel_and_time() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--select").arg("hours=0.01-1,ch=100-200").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "ch=101 " ).and(predicate::str::contains( "ch=7 " ).not()))
+        .stderr(predicate::str::contains( "tests/test.fastq: 7 reads discarded" ) );
+
+    Ok(())
+}
+
+#[test]
+fn select_hours_from_given_run_start() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--select").arg("hours=0.3-0.31").arg("--run_start").arg("2019-10-30T10:00:00Z").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "@a4ed205e-52e6-4a51-aecb-4153e9302224" ) )
+        .stderr(predicate::str::contains( "8 reads discarded" ) );
+
+    Ok(())
+}
+
+#[test]
+fn select_first_hours() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--select").arg("hours=0.005").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains( "8 reads discarded" ) );
+
+    Ok(())
+}
+
+#[test]
+fn illumina_lanes_without_filtered_reads() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--group_by").arg("lane").arg("--skip_filtered").arg("--columns").arg("reads,Q30_percent").arg("tests/illumina.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "lane\treads\tQ30_percent\n1\t2\t75.00\n2\t2\t75.00\n" ))
+        .stderr(predicate::str::contains( "tests/illumina.fastq: 1 filtered reads skipped" ) );
+
+    Ok(())
+}
+
+#[test]
+fn length_quality_histogram() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--len_qual").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "tests/test.fastq\t399\t8\t1\t453\ntests/test.fastq\t399\t10\t1\t421\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn html_report() -> Result<(), Box<dyn std::error::Error>> {
+
+    let report = std::env::temp_dir().join("faster_html_report_test.html");
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--html").arg(&report).arg("tests/test.fastq").arg("tests/test.fasta");
+
+    cmd.assert()
+        .success();
+
+    let html = std::fs::read_to_string(&report)?;
+    assert!(html.contains("<h2>tests/test.fastq</h2>") && html.contains("<h2>tests/test.fasta</h2>"));
+    assert_eq!(html.matches("<svg").count(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn compare_two_files() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--compare").arg("tests/test.fastq").arg("tests/test.fastq.bgz");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "reads\t10\t10\t0\t0.00\n" )
+            .and(predicate::str::contains( "only_in_first\t0\nonly_in_second\t0\nin_both\t10\nchanged_length\t0\nchanged_quality\t0\n" )) );
+
+    Ok(())
+}
+
+#[test]
+fn compare_counts_duplicate_ids() -> Result<(), Box<dyn std::error::Error>> {
+
+    let reads = std::fs::read_to_string("tests/test.fastq")?;
+    let twice = std::env::temp_dir().join("faster_compare_duplicates.fastq");
+    std::fs::write(&twice, reads.repeat(2))?;
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--compare").arg(&twice).arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "only_in_first\t0\nonly_in_second\t0\nin_both\t10\n" ) )
+        .stdout(predicate::str::contains( "duplicates_in_first\t10\nduplicates_in_second\t0\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn watch_directory() -> Result<(), Box<dyn std::error::Error>> {
+
+    let dir = std::env::temp_dir().join("faster_watch_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::copy("tests/test.fastq", dir.join("chunk_0.fastq"))?;
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--watch").arg("--interval").arg("1").arg("--idle").arg("1").arg("--columns").arg("reads,bases").arg(&dir);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "reads\tbases\n10\t18931\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn watch_conflicts_with_save_stats() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--watch").arg("--save_stats").arg("s.gz").arg("tests");
+
+    cmd.assert()
+        .code(2)
+        .stderr(predicate::str::contains( "cannot be used with" ) );
+
+    Ok(())
+}
+
+#[test]
+fn save_and_merge_stats() -> Result<(), Box<dyn std::error::Error>> {
+
+    let dir = std::env::temp_dir();
+    let a = dir.join("faster_merge_test_a.stats");
+    let b = dir.join("faster_merge_test_b.stats");
+    Command::cargo_bin("faster")?.arg("-t").arg("--save_stats").arg(&a).arg("tests/test.fastq").assert().success();
+    Command::cargo_bin("faster")?.arg("-t").arg("--save_stats").arg(&b).arg("tests/test.fastq.bgz").assert().success();
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--merge_stats").arg(&a).arg(&b);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "total\t20\t37862\t0\t165\t8490\t1893.10\t249\t453\t2440\t5263\t51.45\t16.33\t3\t38.19\t0" ) );
+
+    Ok(())
+}
+
+#[test]
+fn merge_stats_with_and_without_complexity() -> Result<(), Box<dyn std::error::Error>> {
+
+    let dir = std::env::temp_dir();
+    let a = dir.join("faster_merge_complexity_a.stats");
+    let b = dir.join("faster_merge_complexity_b.stats");
+    Command::cargo_bin("faster")?.arg("-t").arg("--columns").arg("reads,complexity").arg("--save_stats").arg(&a).arg("tests/test.fastq").assert().success();
+    Command::cargo_bin("faster")?.arg("-t").arg("--save_stats").arg(&b).arg("tests/test.fastq").assert().success();
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--merge_stats").arg("--columns").arg("reads,complexity").arg(&a).arg(&b);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "20\tNA\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn several_nx_values() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("--nx").arg("0.1,0.5,0.9").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "N10\t8490\tL10\t1\nN50\t5263\tL50\t2\nN90\t453\tL90\t5\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn genome_size_columns() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--genome_size").arg("1k").arg("--columns").arg("reads,NG50,LG50,depth,depth_1k").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "10\t8490\t1\t18.93\t16.19\n" ) );
+
+    Ok(())
+}
+
+#[test]
+fn genome_size_keeps_selected_columns() -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut cmd = Command::cargo_bin("faster")?;
+    cmd.arg("-t").arg("--genome_size").arg("5m").arg("--columns").arg("file,reads").arg("tests/test.fastq");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains( "file\treads\ntests/test.fastq\t10\n" ) );
+
+    Ok(())
+}